@@ -0,0 +1,231 @@
+//! Token budget planning.
+//!
+//! Decides which files fit under a `--max-tokens` limit. Files are ranked by
+//! priority (user patterns first, then READMEs and manifests, source files and
+//! finally tests), included whole while they fit, and the highest-priority
+//! leftover file is truncated to fill any remaining budget.
+
+use glob::{MatchOptions, Pattern};
+
+use crate::config::ConfigError;
+use crate::tokens::Tokenizer;
+use crate::units::format_count;
+
+/// Smallest remaining budget worth filling with a truncated file.
+const MIN_TRUNCATED_TOKENS: usize = 256;
+
+/// File names ranked ahead of everything else.
+const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "setup.cfg",
+    "requirements.txt",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "Gemfile",
+    "composer.json",
+    "CMakeLists.txt",
+    "Makefile",
+    "Dockerfile",
+];
+
+/// Directory names that mark test code.
+const TEST_DIRS: &[&str] = &["test", "tests", "spec", "specs", "__tests__", "testdata"];
+
+/// Default priority tiers, used after any user patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Tier {
+    Overview,
+    Source,
+    Test,
+}
+
+/// Ranks files for inclusion under a token budget.
+#[derive(Debug, Clone, Default)]
+pub struct PriorityRules {
+    /// User patterns, highest priority first
+    patterns: Vec<Pattern>,
+}
+
+impl PriorityRules {
+    /// Creates priority rules from user glob patterns, highest priority first.
+    pub fn new(patterns: &[String]) -> Result<Self, ConfigError> {
+        let patterns = patterns
+            .iter()
+            .map(|p| Pattern::new(p).map_err(|e| ConfigError::InvalidPattern(p.clone(), e.msg.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// Returns the rank of a path relative to its input directory; lower ranks come first.
+    pub fn rank(&self, path: &str) -> usize {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::default()
+        };
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        if let Some(index) = self.patterns.iter().position(|p| {
+            p.matches_with(path, options) || p.matches_with(name, options)
+        }) {
+            return index;
+        }
+
+        self.patterns.len() + default_tier(path) as usize
+    }
+}
+
+/// Classifies a path into a default priority tier.
+fn default_tier(path: &str) -> Tier {
+    let components: Vec<&str> = path.split('/').collect();
+    let name = components.last().copied().unwrap_or(path);
+    let lower = name.to_lowercase();
+
+    if lower.starts_with("readme") || MANIFESTS.contains(&name) {
+        return Tier::Overview;
+    }
+
+    let in_test_dir = components[..components.len() - 1]
+        .iter()
+        .any(|dir| TEST_DIRS.contains(&dir.to_lowercase().as_str()));
+    let stem = lower.split('.').next().unwrap_or(&lower);
+    let test_name = stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tests")
+        || lower.contains(".test.")
+        || lower.contains(".spec.");
+
+    if in_test_dir || test_name {
+        Tier::Test
+    } else {
+        Tier::Source
+    }
+}
+
+/// What to do with a file under the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Include the whole file
+    Include,
+    /// Include at most this many tokens of the file
+    Truncate(usize),
+    /// Leave the file out
+    Drop,
+}
+
+/// Assigns a decision to each file so that the total stays within `budget`.
+///
+/// # Arguments
+/// * `candidates` - `(rank, tokens)` for each file, in output order
+/// * `budget` - Maximum number of tokens to spend
+pub fn plan(candidates: &[(usize, usize)], budget: usize) -> Vec<Decision> {
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by_key(|&i| (candidates[i].0, i));
+
+    let mut decisions = vec![Decision::Drop; candidates.len()];
+    let mut remaining = budget;
+
+    for &i in &order {
+        let tokens = candidates[i].1;
+        if tokens <= remaining {
+            decisions[i] = Decision::Include;
+            remaining -= tokens;
+        }
+    }
+
+    if remaining >= MIN_TRUNCATED_TOKENS {
+        if let Some(&i) = order.iter().find(|&&i| decisions[i] == Decision::Drop) {
+            decisions[i] = Decision::Truncate(remaining);
+        }
+    }
+
+    decisions
+}
+
+/// Cuts text at a line boundary so that it fits within `limit` tokens, marker included.
+///
+/// Returns the truncated text, its token count and the number of omitted lines.
+pub fn truncate_to_tokens(text: &str, limit: usize, tokenizer: Tokenizer) -> (String, usize, usize) {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+
+    let build = |kept: usize| {
+        let head: String = lines[..kept].concat();
+        let omitted = lines.len() - kept;
+        let newline = if head.is_empty() || head.ends_with('\n') { "" } else { "\n" };
+        format!(
+            "{}{}… {} lines omitted to fit the token budget …\n",
+            head,
+            newline,
+            format_count(omitted),
+        )
+    };
+
+    let (mut low, mut high) = (0, lines.len());
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if tokenizer.count(&build(mid)) <= limit {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    let truncated = build(low);
+    let tokens = tokenizer.count(&truncated);
+    (truncated, tokens, lines.len() - low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_tiers() {
+        let rules = PriorityRules::default();
+        assert_eq!(rules.rank("README.md"), 0);
+        assert_eq!(rules.rank("crates/core/Cargo.toml"), 0);
+        assert_eq!(rules.rank("src/main.rs"), 1);
+        assert_eq!(rules.rank("tests/cli.rs"), 2);
+        assert_eq!(rules.rank("src/parser_test.go"), 2);
+        assert_eq!(rules.rank("web/app.spec.ts"), 2);
+    }
+
+    #[test]
+    fn test_user_patterns_rank_first() {
+        let rules = PriorityRules::new(&["src/**/*.rs".to_string(), "*.md".to_string()]).unwrap();
+        assert_eq!(rules.rank("src/io/mod.rs"), 0);
+        assert_eq!(rules.rank("docs/guide.md"), 1);
+        assert_eq!(rules.rank("Cargo.toml"), 2);
+        assert_eq!(rules.rank("build.rs"), 3);
+    }
+
+    #[test]
+    fn test_plan() {
+        let candidates = [(1, 300), (0, 100), (2, 50), (1, 5000)];
+        let decisions = plan(&candidates, 1000);
+
+        assert_eq!(decisions[0], Decision::Include);
+        assert_eq!(decisions[1], Decision::Include);
+        assert_eq!(decisions[2], Decision::Include);
+        assert_eq!(decisions[3], Decision::Truncate(550));
+
+        let decisions = plan(&candidates, 420);
+        assert_eq!(decisions, vec![Decision::Include, Decision::Include, Decision::Drop, Decision::Drop]);
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        let text = "line of text\n".repeat(100);
+        let (truncated, tokens, omitted) = truncate_to_tokens(&text, 60, Tokenizer::Estimate);
+
+        assert!(tokens <= 60);
+        assert_eq!(truncated.lines().count(), 101 - omitted);
+        assert!(truncated.starts_with("line of text\n"));
+        assert!(truncated.ends_with("lines omitted to fit the token budget …\n"));
+    }
+}
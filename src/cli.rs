@@ -1,7 +1,11 @@
 //! Command line interface definitions.
 
+use std::path::PathBuf;
+
 use clap::Parser;
 
+use vitax::{parse_size, FollowSymlinks, OutputFormat, Preset, Settings, Tokenizer};
+
 /// Command line arguments for vitax.
 #[derive(Parser, Debug, Clone)]
 #[command(name = "vitax")]
@@ -11,9 +15,13 @@ pub struct Args {
     /// Input paths to analyze
     pub paths: Vec<String>,
 
-    /// Maximum recursion depth
-    #[arg(short = 'd', long = "depth", default_value = "10")]
-    pub max_depth: usize,
+    /// Maximum recursion depth [default: 10]
+    #[arg(short = 'd', long = "depth")]
+    pub max_depth: Option<usize>,
+
+    /// Ignore and include rules for an ecosystem, or auto to detect (can be used multiple times)
+    #[arg(long = "preset", value_enum, value_name = "PRESET")]
+    pub preset: Vec<Preset>,
 
     /// Patterns to ignore (can be used multiple times)
     #[arg(short = 'I', long = "ignore")]
@@ -23,11 +31,212 @@ pub struct Args {
     #[arg(short = 'e', long = "ext")]
     pub extensions: Vec<String>,
 
+    /// Only include files whose path relative to the input matches a glob, e.g. 'src/**/*.rs' (can be used multiple times)
+    #[arg(long = "include", value_name = "PATTERN")]
+    pub include: Vec<String>,
+
     /// Show hidden files and directories
-    #[arg(short = 'a', long = "all")]
+    #[arg(short = 'a', long = "all", overrides_with = "hide_hidden")]
     pub show_hidden: bool,
 
+    /// Hide hidden files and directories, overriding -a from a configuration file
+    #[arg(long = "no-all", overrides_with = "show_hidden")]
+    pub hide_hidden: bool,
+
+    /// Do not respect .gitignore, .ignore and git exclude files
+    #[arg(long = "no-ignore", overrides_with = "ignore_files")]
+    pub no_ignore: bool,
+
+    /// Respect .gitignore, .ignore and git exclude files, overriding no-ignore from a configuration file
+    #[arg(long = "ignore-files", overrides_with = "no_ignore")]
+    pub ignore_files: bool,
+
+    /// Output format [default: plain]
+    #[arg(short = 'f', long = "format", value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Print a directory tree before the file contents
+    #[arg(long = "tree", overrides_with = "no_tree")]
+    pub tree: bool,
+
+    /// Print only the directory tree, with file sizes
+    #[arg(long = "tree-only", conflicts_with = "tree", overrides_with = "no_tree")]
+    pub tree_only: bool,
+
+    /// Print no directory tree, overriding tree or tree-only from a configuration file
+    #[arg(long = "no-tree", overrides_with_all = ["tree", "tree_only"])]
+    pub no_tree: bool,
+
+    /// Count tokens per file and summarize them (cl100k, o200k or estimate)
+    #[arg(long = "tokens", value_enum, value_name = "TOKENIZER", num_args = 0..=1, require_equals = true, default_missing_value = "cl100k", overrides_with = "no_tokens")]
+    pub tokens: Option<Tokenizer>,
+
+    /// Count no tokens per file, overriding tokens from a configuration file (--max-tokens still counts)
+    #[arg(long = "no-tokens", overrides_with = "tokens")]
+    pub no_tokens: bool,
+
+    /// Maximum size of a single file, e.g. 200K or 5M [default: 10M]
+    #[arg(long = "max-size", value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// Maximum total size of file contents in the output, e.g. 2M
+    #[arg(long = "max-total-size", value_name = "SIZE", value_parser = parse_size)]
+    pub max_total_size: Option<u64>,
+
+    /// Show only the first and last lines of files longer than N lines
+    #[arg(long = "max-lines", value_name = "N", conflicts_with_all = ["head", "tail"])]
+    pub max_lines: Option<usize>,
+
+    /// Lines to keep from the beginning of long files
+    #[arg(long = "head", value_name = "N")]
+    pub head: Option<usize>,
+
+    /// Lines to keep from the end of long files
+    #[arg(long = "tail", value_name = "N")]
+    pub tail: Option<usize>,
+
+    /// Fit the output under a token budget, dropping or truncating lower-priority files
+    #[arg(long = "max-tokens", value_name = "N")]
+    pub max_tokens: Option<usize>,
+
+    /// Glob patterns to prioritize under --max-tokens, highest first (can be used multiple times)
+    #[arg(long = "priority", value_name = "PATTERN")]
+    pub priority: Vec<String>,
+
+    /// Write the output to this file instead of stdout, replacing it only once the run succeeds
+    #[arg(short = 'o', long = "output", value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Split the output into numbered part files of at most this size, e.g. 500K (requires --output)
+    #[arg(long = "split-size", value_name = "SIZE", value_parser = parse_size)]
+    pub split_size: Option<u64>,
+
+    /// Split the output into numbered part files of about this many tokens (requires --output)
+    #[arg(long = "split-tokens", value_name = "N")]
+    pub split_tokens: Option<usize>,
+
+    /// Read settings from this file instead of vitax.toml and the user config
+    #[arg(long = "config", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Apply the named [profile.<name>] settings from the configuration files
+    #[arg(long = "profile", value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Follow symlinks (all, or within-root to refuse links leading outside the input directory)
+    #[arg(long = "follow-symlinks", value_enum, value_name = "WHICH", num_args = 0..=1, require_equals = true, default_missing_value = "all", overrides_with = "no_follow_symlinks")]
+    pub follow_symlinks: Option<FollowSymlinks>,
+
+    /// Follow no symlinks, overriding follow-symlinks from a configuration file
+    #[arg(long = "no-follow-symlinks", overrides_with = "follow_symlinks")]
+    pub no_follow_symlinks: bool,
+
+    /// Number of files to read and classify in parallel [default: number of CPUs]
+    #[arg(short = 'j', long = "jobs", value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Print statistics by outcome and language after the run (included in the summary for json and jsonl)
+    #[arg(long = "stats", overrides_with = "no_stats")]
+    pub stats: bool,
+
+    /// Print no statistics, overriding stats from a configuration file
+    #[arg(long = "no-stats", overrides_with = "stats")]
+    pub no_stats: bool,
+
+    /// Stop with an error at the first unreadable directory instead of skipping it
+    #[arg(long = "strict", overrides_with = "no_strict")]
+    pub strict: bool,
+
+    /// Skip unreadable directories, overriding strict from a configuration file
+    #[arg(long = "no-strict", overrides_with = "strict")]
+    pub no_strict: bool,
+
     /// Verbose output (show skipped files and errors)
-    #[arg(short = 'v', long = "verbose")]
+    #[arg(short = 'v', long = "verbose", overrides_with = "quiet")]
     pub verbose: bool,
-}
\ No newline at end of file
+
+    /// Turn off verbose output, overriding verbose from a configuration file
+    #[arg(long = "no-verbose", overrides_with = "verbose")]
+    pub quiet: bool,
+}
+
+impl Args {
+    /// Returns the command line layer of the settings.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            preset: self.preset.clone(),
+            ignore: self.ignore.clone(),
+            extensions: self.extensions.clone(),
+            include: self.include.clone(),
+            depth: self.max_depth,
+            all: switch(self.show_hidden, self.hide_hidden),
+            no_ignore: switch(self.no_ignore, self.ignore_files),
+            format: self.format,
+            tree: switch(self.tree, self.no_tree),
+            tree_only: switch(self.tree_only, self.no_tree),
+            tokens: self.tokens,
+            no_tokens: switch(self.no_tokens, self.tokens.is_some()),
+            max_size: self.max_size,
+            max_total_size: self.max_total_size,
+            max_lines: self.max_lines,
+            head: self.head,
+            tail: self.tail,
+            max_tokens: self.max_tokens,
+            priority: self.priority.clone(),
+            follow_symlinks: self.follow_symlinks,
+            no_follow_symlinks: switch(self.no_follow_symlinks, self.follow_symlinks.is_some()),
+            jobs: self.jobs,
+            output: self.output.clone(),
+            split_size: self.split_size,
+            split_tokens: self.split_tokens,
+            stats: switch(self.stats, self.no_stats),
+            strict: switch(self.strict, self.no_strict),
+            verbose: switch(self.verbose, self.quiet),
+        }
+    }
+}
+
+/// Maps a flag and its negation to a setting: on, off, or left to the layers below.
+fn switch(on: bool, off: bool) -> Option<bool> {
+    if on {
+        Some(true)
+    } else if off {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vitax::{Config, ConfigFile};
+
+    #[test]
+    fn test_cli_overrides_file_flags() {
+        let file = ConfigFile::parse("all = true\ntree = true\nstats = true\nverbose = true\n").unwrap().settings;
+        let args = Args::parse_from(["vitax", ".", "--no-all", "--no-tree", "--no-stats", "--stats", "--no-verbose"]);
+        let merged = file.clone().merge(args.settings());
+        assert_eq!(merged.all, Some(false));
+        assert_eq!((merged.tree, merged.tree_only), (Some(false), Some(false)));
+        assert_eq!(merged.stats, Some(true));
+        assert_eq!(merged.verbose, Some(false));
+
+        let args = Args::parse_from(["vitax", ".", "--no-tree", "--tree-only"]);
+        let merged = file.clone().merge(args.settings());
+        assert_eq!((merged.tree, merged.tree_only), (None, Some(true)));
+
+        let args = Args::parse_from(["vitax", "."]);
+        let merged = file.merge(args.settings());
+        assert_eq!((merged.all, merged.tree, merged.verbose), (Some(true), Some(true), Some(true)));
+
+        let file = ConfigFile::parse("tokens = \"o200k\"\nfollow-symlinks = \"all\"\n").unwrap().settings;
+        let args = Args::parse_from(["vitax", ".", "--no-tokens", "--no-follow-symlinks"]);
+        let config = Config::from_settings(vec![".".to_string()], file.clone().merge(args.settings())).unwrap();
+        assert_eq!((config.tokenizer, config.follow_symlinks), (None, None));
+
+        let args = Args::parse_from(["vitax", ".", "--no-follow-symlinks", "--follow-symlinks=within-root"]);
+        let config = Config::from_settings(vec![".".to_string()], file.merge(args.settings())).unwrap();
+        assert_eq!(config.follow_symlinks, Some(FollowSymlinks::WithinRoot));
+    }
+}
@@ -3,11 +3,24 @@
 //! This module manages the application's configuration,
 //! validates user inputs, and creates the necessary components.
 
-use crate::cli::Args;
+use crate::budget::PriorityRules;
+use crate::destination::PartLimit;
+use crate::excerpt::LineExcerpt;
 use crate::filter::FileFilter;
-use std::path::Path;
+use crate::io::FollowSymlinks;
+use crate::output::OutputFormat;
+use crate::pool;
+use crate::preset::Preset;
+use crate::settings::Settings;
+use crate::tokens::Tokenizer;
+use crate::tree::TreeMode;
+use crate::validator::DEFAULT_MAX_FILE_SIZE;
+use std::path::{Path, PathBuf};
 
-/// Application configuration built from CLI arguments.
+/// Default maximum recursion depth.
+pub const DEFAULT_MAX_DEPTH: usize = 10;
+
+/// Application configuration built from CLI arguments and configuration files.
 #[derive(Debug)]
 pub struct Config {
     /// Paths to process
@@ -16,51 +29,156 @@ pub struct Config {
     pub max_depth: usize,
     /// File filter instance
     pub filter: FileFilter,
+    /// Whether to honor .gitignore, .ignore and git exclude files
+    pub use_ignore_files: bool,
+    /// Output format
+    pub format: OutputFormat,
+    /// Directory tree display mode
+    pub tree: TreeMode,
+    /// Tokenizer for per-file token counts, if enabled
+    pub tokenizer: Option<Tokenizer>,
+    /// Maximum size of a single file in bytes
+    pub max_file_size: u64,
+    /// Maximum total size of file contents in the output
+    pub max_total_size: Option<u64>,
+    /// Head and tail excerpt for long files, if enabled
+    pub excerpt: Option<LineExcerpt>,
+    /// Token budget for the whole output
+    pub max_tokens: Option<usize>,
+    /// File priorities used when applying the token budget
+    pub priority: PriorityRules,
+    /// Which symlinks to follow, if any
+    pub follow_symlinks: Option<FollowSymlinks>,
+    /// Number of worker threads reading and classifying files
+    pub jobs: usize,
+    /// File to write the output to instead of stdout
+    pub output: Option<PathBuf>,
+    /// Limits for numbered part files, if the output is split
+    pub split: Option<PartLimit>,
+    /// Collect statistics by outcome and language
+    pub stats: bool,
+    /// Stop at the first unreadable directory or entry
+    pub strict: bool,
     /// Verbose output mode
     pub verbose: bool,
+    /// Configuration files that were applied, lowest precedence first
+    pub config_files: Vec<PathBuf>,
+    /// Presets whose rules were applied, with `auto` resolved
+    pub presets: Vec<Preset>,
 }
 
 impl Config {
-    /// Creates a new configuration from CLI arguments.
+    /// Creates a new configuration from a single layer of settings.
     ///
-    /// # Arguments
-    /// * `args` - Parsed command line arguments
+    /// Presets are resolved and applied below the settings, but no
+    /// configuration files are read.
     ///
-    /// Returns `Ok(Config)` if validation passes, `Err(ConfigError)` otherwise.
-    pub fn from_args(args: Args) -> Result<Self, ConfigError> {
-        Self::validate(&args)?;
+    /// # Arguments
+    /// * `paths` - Paths to process
+    /// * `settings` - Merged settings
+    pub fn from_settings(paths: Vec<String>, settings: Settings) -> Result<Self, ConfigError> {
+        let presets = Preset::resolve(&settings.preset, &paths);
+        let settings = Preset::settings(&presets).merge(settings);
+
+        Self::validate(&paths, &settings)?;
+
+        let priority = PriorityRules::new(&settings.priority)?;
+
+        let tokens = settings.tokens.filter(|_| settings.no_tokens != Some(true));
+        let tokenizer = match (tokens, settings.max_tokens) {
+            (None, Some(_)) => Some(Tokenizer::Cl100k),
+            (tokenizer, _) => tokenizer,
+        };
 
         let filter = FileFilter::new(
-            args.extensions,
-            args.ignore,
-            args.show_hidden,
-        );
+            settings.extensions,
+            &settings.include,
+            settings.ignore,
+            settings.all.unwrap_or(false),
+        )?;
 
         Ok(Self {
-            paths: args.paths,
-            max_depth: args.max_depth,
+            paths,
+            max_depth: settings.depth.unwrap_or(DEFAULT_MAX_DEPTH),
             filter,
-            verbose: args.verbose,
+            use_ignore_files: !settings.no_ignore.unwrap_or(false),
+            format: settings.format.unwrap_or(OutputFormat::Plain),
+            tree: if settings.tree_only == Some(true) {
+                TreeMode::Only
+            } else if settings.tree == Some(true) {
+                TreeMode::WithContents
+            } else {
+                TreeMode::Off
+            },
+            tokenizer,
+            max_file_size: settings.max_size.unwrap_or(DEFAULT_MAX_FILE_SIZE),
+            max_total_size: settings.max_total_size,
+            excerpt: match (settings.max_lines, settings.head, settings.tail) {
+                (Some(max_lines), _, _) => Some(LineExcerpt::from_max_lines(max_lines)),
+                (None, None, None) => None,
+                (None, head, tail) => Some(LineExcerpt {
+                    head: head.unwrap_or(0),
+                    tail: tail.unwrap_or(0),
+                }),
+            },
+            max_tokens: settings.max_tokens,
+            priority,
+            follow_symlinks: settings.follow_symlinks.filter(|_| settings.no_follow_symlinks != Some(true)),
+            jobs: settings.jobs.unwrap_or_else(pool::default_jobs),
+            split: (settings.split_size.is_some() || settings.split_tokens.is_some()).then(|| PartLimit {
+                bytes: settings.split_size,
+                tokens: settings.split_tokens,
+                tokenizer: tokenizer.unwrap_or(Tokenizer::Cl100k),
+            }),
+            output: settings.output,
+            stats: settings.stats.unwrap_or(false),
+            strict: settings.strict.unwrap_or(false),
+            verbose: settings.verbose.unwrap_or(false),
+            config_files: Vec::new(),
+            presets,
         })
     }
 
-    /// Validates CLI arguments for correctness.
-    fn validate(args: &Args) -> Result<(), ConfigError> {
-        if args.paths.is_empty() {
+    /// Validates input paths and merged settings for correctness.
+    fn validate(paths: &[String], settings: &Settings) -> Result<(), ConfigError> {
+        if paths.is_empty() {
             return Err(ConfigError::NoInputFiles);
         }
 
-        if args.max_depth == 0 {
+        if settings.depth == Some(0) {
             return Err(ConfigError::InvalidDepth);
         }
 
-        for path in &args.paths {
+        if settings.jobs == Some(0) {
+            return Err(ConfigError::InvalidJobCount);
+        }
+
+        if settings.max_tokens == Some(0) {
+            return Err(ConfigError::InvalidTokenBudget);
+        }
+
+        let excerpt_lines = settings.head.unwrap_or(0) + settings.tail.unwrap_or(0);
+        if settings.max_lines == Some(0)
+            || ((settings.head.is_some() || settings.tail.is_some()) && excerpt_lines == 0)
+        {
+            return Err(ConfigError::InvalidLineLimit);
+        }
+
+        if settings.max_size == Some(0) || settings.max_total_size == Some(0) {
+            return Err(ConfigError::InvalidSizeLimit);
+        }
+
+        if settings.split_size.is_some() || settings.split_tokens.is_some() {
+            Self::validate_split(settings)?;
+        }
+
+        for path in paths {
             if !Path::new(path).exists() {
                 return Err(ConfigError::PathNotFound(path.clone()));
             }
         }
 
-        for ext in &args.extensions {
+        for ext in &settings.extensions {
             if ext.is_empty() {
                 return Err(ConfigError::EmptyExtension);
             }
@@ -75,7 +193,7 @@ impl Config {
             if ext.contains('*') {
                 return Err(ConfigError::InvalidExtension(
                     ext.clone(),
-                    "should not contain wildcards".to_string()
+                    "should not contain wildcards (use --include for glob patterns)".to_string()
                 ));
             }
         }
@@ -83,6 +201,27 @@ impl Config {
         Ok(())
     }
 
+    /// Validates the options for splitting the output into part files.
+    fn validate_split(settings: &Settings) -> Result<(), ConfigError> {
+        if settings.output.is_none() {
+            return Err(ConfigError::InvalidSplit("part files require --output".to_string()));
+        }
+
+        if settings.split_size == Some(0) || settings.split_tokens == Some(0) {
+            return Err(ConfigError::InvalidSplit("part limits must be at least 1".to_string()));
+        }
+
+        match settings.format {
+            Some(OutputFormat::Json) => Err(ConfigError::InvalidSplit(
+                "json output is a single document (use --format jsonl)".to_string(),
+            )),
+            Some(OutputFormat::Xml) => Err(ConfigError::InvalidSplit(
+                "xml output is a single document".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     /// Returns true if any filters are active.
     pub fn has_filters(&self) -> bool {
         self.filter.has_filters()
@@ -107,6 +246,22 @@ pub enum ConfigError {
     EmptyExtension,
     /// Invalid extension format
     InvalidExtension(String, String),
+    /// Worker thread count of zero
+    InvalidJobCount,
+    /// Token budget of zero
+    InvalidTokenBudget,
+    /// Size limit of zero
+    InvalidSizeLimit,
+    /// Line limit that would show nothing
+    InvalidLineLimit,
+    /// Output split that cannot be written, with the reason
+    InvalidSplit(String),
+    /// Malformed glob pattern
+    InvalidPattern(String, String),
+    /// Unreadable or malformed configuration file
+    ConfigFile(String, String),
+    /// Profile not defined in any configuration file, with the available names
+    UnknownProfile(String, Vec<String>),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -119,6 +274,23 @@ impl std::fmt::Display for ConfigError {
             ConfigError::InvalidExtension(ext, reason) => {
                 write!(f, "invalid extension '{}': {}", ext, reason)
             }
+            ConfigError::InvalidJobCount => write!(f, "jobs must be at least 1"),
+            ConfigError::InvalidTokenBudget => write!(f, "token budget must be at least 1"),
+            ConfigError::InvalidSizeLimit => write!(f, "size limits must be at least 1 byte"),
+            ConfigError::InvalidLineLimit => write!(f, "line limits must keep at least 1 line"),
+            ConfigError::InvalidSplit(reason) => write!(f, "cannot split output: {}", reason),
+            ConfigError::InvalidPattern(pattern, reason) => {
+                write!(f, "invalid pattern '{}': {}", pattern, reason)
+            }
+            ConfigError::ConfigFile(path, reason) => {
+                write!(f, "invalid config file '{}': {}", path, reason)
+            }
+            ConfigError::UnknownProfile(name, available) if available.is_empty() => {
+                write!(f, "unknown profile '{}': no profiles are defined", name)
+            }
+            ConfigError::UnknownProfile(name, available) => {
+                write!(f, "unknown profile '{}' (available: {})", name, available.join(", "))
+            }
         }
     }
 }
@@ -0,0 +1,439 @@
+//! Output files.
+//!
+//! Output can go to a file instead of standard output, or be split into
+//! numbered part files for tools with upload limits. Files are written under
+//! temporary names next to their destination and renamed into place only
+//! once the whole run has succeeded, so a failed run leaves any previous
+//! output untouched.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::tokens::Tokenizer;
+use crate::units::{format_count, format_size};
+
+/// A file that replaces its destination only when committed.
+///
+/// Dropping the file without committing it removes the temporary file.
+pub struct AtomicFile {
+    path: PathBuf,
+    temp: PathBuf,
+    writer: Option<BufWriter<File>>,
+    bytes: u64,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// Creates a temporary file in the destination's directory.
+    ///
+    /// # Arguments
+    /// * `path` - The file to replace on commit
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let Some(name) = path.file_name() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a file path", path.display()),
+            ));
+        };
+
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp = path.with_file_name(temp_name);
+
+        let file = File::create(&temp)
+            .map_err(|e| io::Error::new(e.kind(), format!("cannot create {}: {}", temp.display(), e)))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            temp,
+            writer: Some(BufWriter::new(file)),
+            bytes: 0,
+            committed: false,
+        })
+    }
+
+    /// Returns the destination path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Flushes the temporary file to disk and closes it.
+    fn close(&mut self) -> io::Result<()> {
+        if let Some(writer) = self.writer.take() {
+            let file = writer.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    /// Closes the file and renames it over its destination.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.close()?;
+        fs::rename(&self.temp, &self.path)
+            .map_err(|e| io::Error::new(e.kind(), format!("cannot replace {}: {}", self.path.display(), e)))?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Err(io::Error::other("write to a closed output file"));
+        };
+
+        let written = writer.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.writer.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.writer = None;
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Limits for each part of split output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartLimit {
+    /// Maximum size of a part in bytes
+    pub bytes: Option<u64>,
+    /// Maximum number of tokens in a part
+    pub tokens: Option<usize>,
+    /// Tokenizer used to count tokens
+    pub tokenizer: Tokenizer,
+}
+
+/// A file written by a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputPart {
+    /// Where the file was written
+    pub path: PathBuf,
+    /// Size of the file in bytes
+    pub bytes: u64,
+    /// Approximate token count, if parts are limited by tokens
+    pub tokens: Option<usize>,
+    /// Whether a single file's output made the part exceed its limit
+    pub oversized: bool,
+}
+
+impl std::fmt::Display for OutputPart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}", self.path.display(), format_size(self.bytes))?;
+        if let Some(tokens) = self.tokens {
+            write!(f, ", {} tokens", format_count(tokens))?;
+        }
+        write!(f, ")")?;
+        if self.oversized {
+            write!(f, ", over the part limit")?;
+        }
+        Ok(())
+    }
+}
+
+/// A part file being written, with the tokens it holds.
+struct Part {
+    file: AtomicFile,
+    tokens: usize,
+}
+
+/// Splits output into numbered part files next to the output path.
+///
+/// Output is held back until the writer is flushed, and a part only ends
+/// between two flushes. The splittable renderers flush after every file, so
+/// no file is divided between parts; a file whose output alone exceeds the
+/// limit gets a part of its own. Token counts are summed per flush and so
+/// are approximate.
+pub struct SplitWriter {
+    path: PathBuf,
+    limit: PartLimit,
+    pending: Vec<u8>,
+    current: Option<Part>,
+    done: Vec<Part>,
+}
+
+impl SplitWriter {
+    /// Creates a writer for parts named after `path`, e.g. `out.part001.md`.
+    ///
+    /// # Arguments
+    /// * `path` - The output path the part names are derived from
+    /// * `limit` - Limits for each part
+    pub fn new(path: &Path, limit: PartLimit) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            limit,
+            pending: Vec::new(),
+            current: None,
+            done: Vec::new(),
+        }
+    }
+
+    /// Writes any remaining output and moves every part into place.
+    ///
+    /// Every part is flushed and closed before the first one is renamed, so
+    /// a write error leaves all previous parts untouched. Parts left over
+    /// from an earlier run that needed more of them are removed.
+    ///
+    /// Returns the parts in order.
+    pub fn finish(mut self) -> io::Result<Vec<OutputPart>> {
+        self.end_segment()?;
+        self.done.extend(self.current.take());
+
+        for part in &mut self.done {
+            part.file.close()?;
+        }
+
+        let mut parts = Vec::with_capacity(self.done.len());
+        for part in std::mem::take(&mut self.done) {
+            parts.push(OutputPart {
+                path: part.file.path().to_path_buf(),
+                bytes: part.file.bytes(),
+                tokens: self.limit.tokens.map(|_| part.tokens),
+                oversized: self.exceeds(part.file.bytes(), part.tokens),
+            });
+            part.file.commit()?;
+        }
+
+        self.remove_stale_parts(parts.len() + 1)?;
+        Ok(parts)
+    }
+
+    /// Removes consecutive part files from an earlier run, starting at `first`.
+    fn remove_stale_parts(&self, first: usize) -> io::Result<()> {
+        for number in first.. {
+            let path = part_path(&self.path, number);
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => {
+                    return Err(io::Error::new(e.kind(), format!("cannot remove {}: {}", path.display(), e)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves the output written since the last flush into a part.
+    fn end_segment(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let tokens = match self.limit.tokens {
+            Some(_) => self.limit.tokenizer.count(&String::from_utf8_lossy(&self.pending)),
+            None => 0,
+        };
+
+        let mut part = match self.current.take() {
+            Some(part) if !self.exceeds(part.file.bytes() + self.pending.len() as u64, part.tokens + tokens) => part,
+            Some(mut full) => {
+                full.file.close()?;
+                self.done.push(full);
+                self.start_part()?
+            }
+            None => self.start_part()?,
+        };
+
+        part.file.write_all(&self.pending)?;
+        part.tokens += tokens;
+        self.current = Some(part);
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Creates the file for the next part.
+    fn start_part(&self) -> io::Result<Part> {
+        let file = AtomicFile::create(&part_path(&self.path, self.done.len() + 1))?;
+        Ok(Part { file, tokens: 0 })
+    }
+
+    /// Whether a part of the given size would be over the limit.
+    fn exceeds(&self, bytes: u64, tokens: usize) -> bool {
+        self.limit.bytes.is_some_and(|limit| bytes > limit)
+            || self.limit.tokens.is_some_and(|limit| tokens > limit)
+    }
+}
+
+impl Write for SplitWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.end_segment()
+    }
+}
+
+/// Recognizes the files a run writes to, so they are not read back in as input.
+///
+/// Matches the output file, its numbered parts and the temporary files of
+/// either, from this or any earlier run.
+pub(crate) struct OutputFiles {
+    /// Canonical directory the output is written to
+    dir: PathBuf,
+    name: String,
+    stem: String,
+    extension: Option<String>,
+}
+
+impl OutputFiles {
+    /// Creates a matcher for output written to `path`.
+    ///
+    /// Returns `None` if the output directory does not exist, in which case
+    /// no input can be an output file.
+    pub(crate) fn new(path: &Path) -> Option<Self> {
+        Some(Self {
+            dir: fs::canonicalize(parent_dir(path)).ok()?,
+            name: path.file_name()?.to_string_lossy().to_string(),
+            stem: path.file_stem()?.to_string_lossy().to_string(),
+            extension: path.extension().map(|ext| ext.to_string_lossy().to_string()),
+        })
+    }
+
+    /// Whether `path` is the output file, one of its parts, or a temporary file of either.
+    pub(crate) fn contains(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().map(|name| name.to_string_lossy()) else {
+            return false;
+        };
+
+        let name = temp_target(&name).unwrap_or(&name);
+        if name != self.name && !self.is_part(name) {
+            return false;
+        }
+
+        fs::canonicalize(parent_dir(path)).is_ok_and(|dir| dir == self.dir)
+    }
+
+    /// Whether `name` is a part name derived from the output path by `part_path`.
+    fn is_part(&self, name: &str) -> bool {
+        let Some(rest) = name.strip_prefix(&self.stem).and_then(|rest| rest.strip_prefix(".part")) else {
+            return false;
+        };
+
+        let number = match &self.extension {
+            Some(ext) => rest.strip_suffix(ext.as_str()).and_then(|rest| rest.strip_suffix('.')),
+            None => Some(rest),
+        };
+        number.is_some_and(|number| number.len() >= 3 && number.bytes().all(|b| b.is_ascii_digit()))
+    }
+}
+
+/// Returns the destination name of a temporary file named by `AtomicFile`,
+/// `.<name>.<pid>.tmp`.
+fn temp_target(name: &str) -> Option<&str> {
+    let (target, pid) = name.strip_prefix('.')?.strip_suffix(".tmp")?.rsplit_once('.')?;
+    (!pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit())).then_some(target)
+}
+
+/// Returns the directory containing `path`, `.` for a bare file name.
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Returns the path of a numbered part, inserting `.partNNN` before the extension.
+fn part_path(path: &Path, number: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.part{:03}.{}", stem, number, ext.to_string_lossy()),
+        None => format!("{}.part{:03}", stem, number),
+    };
+
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_atomic_file() {
+        let dir = TempDir::new("atomic");
+        let path = dir.join("out.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"new\n").unwrap();
+        drop(file);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\n");
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"new\n").unwrap();
+        file.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_split_writer() {
+        let dir = TempDir::new("split");
+        let limit = PartLimit { bytes: Some(10), tokens: None, tokenizer: Tokenizer::Estimate };
+        let mut writer = SplitWriter::new(&dir.join("out.md"), limit);
+
+        for segment in ["aaaa\n", "bbbb\n", "cccc\n", "a much longer segment\n", "dd\n"] {
+            writer.write_all(segment.as_bytes()).unwrap();
+            writer.flush().unwrap();
+        }
+        assert!(!dir.join("out.part001.md").exists());
+
+        let parts = writer.finish().unwrap();
+        let names: Vec<_> = parts.iter().map(|p| p.path.file_name().unwrap().to_string_lossy()).collect();
+        assert_eq!(names, ["out.part001.md", "out.part002.md", "out.part003.md", "out.part004.md"]);
+        assert_eq!(fs::read_to_string(&parts[0].path).unwrap(), "aaaa\nbbbb\n");
+        assert_eq!(fs::read_to_string(&parts[1].path).unwrap(), "cccc\n");
+        assert!(parts[2].oversized);
+        assert_eq!(parts[3].bytes, 3);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+
+        let mut writer = SplitWriter::new(&dir.join("out.md"), limit);
+        writer.write_all(b"short\n").unwrap();
+        let parts = writer.finish().unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(fs::read_to_string(dir.join("out.part001.md")).unwrap(), "short\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_output_files() {
+        let dir = TempDir::new("output-files");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+
+        let output = OutputFiles::new(&dir.join("sub/../out.md")).unwrap();
+        for name in ["out.md", "out.part001.md", "out.part1234.md", ".out.md.42.tmp", ".out.part002.md.42.tmp"] {
+            assert!(output.contains(&dir.join(name)), "{}", name);
+        }
+        for name in ["out.txt", "out.part01.md", "out.partxyz.md", "other.md", ".out.md.tmp", "sub/out.md"] {
+            assert!(!output.contains(&dir.join(name)), "{}", name);
+        }
+
+        let output = OutputFiles::new(&dir.join("dump")).unwrap();
+        assert!(output.contains(&dir.join("dump.part003")));
+        assert!(!output.contains(&dir.join("dump.part003.md")));
+        assert!(OutputFiles::new(&dir.join("missing/out.md")).is_none());
+    }
+}
@@ -1,7 +1,10 @@
-use std::fs;
 use std::io;
 
-const BINARY_CHECK_BYTES: usize = 2048;
+use encoding_rs::{EUC_JP, ISO_2022_JP, SHIFT_JIS, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+/// Number of leading bytes inspected to classify a file and detect its encoding.
+pub const BINARY_CHECK_BYTES: usize = 2048;
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
 
 #[derive(Debug, PartialEq)]
 pub enum FileType {
@@ -12,47 +15,234 @@ pub enum FileType {
 #[derive(Debug, PartialEq)]
 pub enum Encoding {
     Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
     ShiftJis,
+    EucJp,
+    Iso2022Jp,
+    Windows1252,
     Unknown,
 }
 
+impl Encoding {
+    /// Decodes raw file bytes into a UTF-8 string.
+    ///
+    /// Malformed sequences in legacy encodings are replaced with U+FFFD, while
+    /// invalid UTF-8 and unknown encodings are reported as `InvalidData` errors.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, io::Error> {
+        let legacy = match self {
+            Encoding::Utf8 | Encoding::Unknown => return Self::decode_utf8(bytes),
+            Encoding::Utf8Bom => {
+                return Self::decode_utf8(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes));
+            }
+            Encoding::Utf16Le => UTF_16LE,
+            Encoding::Utf16Be => UTF_16BE,
+            Encoding::ShiftJis => SHIFT_JIS,
+            Encoding::EucJp => EUC_JP,
+            Encoding::Iso2022Jp => ISO_2022_JP,
+            Encoding::Windows1252 => WINDOWS_1252,
+        };
+
+        let (text, _) = legacy.decode_with_bom_removal(bytes);
+        Ok(text.into_owned())
+    }
+
+    /// Returns true for the UTF-16 encodings, whose text contains NUL bytes.
+    pub fn is_utf16(&self) -> bool {
+        matches!(self, Encoding::Utf16Le | Encoding::Utf16Be)
+    }
+
+    fn decode_utf8(bytes: &[u8]) -> Result<String, io::Error> {
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::ShiftJis => "Shift_JIS",
+            Encoding::EucJp => "EUC-JP",
+            Encoding::Iso2022Jp => "ISO-2022-JP",
+            Encoding::Windows1252 => "Windows-1252",
+            Encoding::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 pub struct FileDetector;
 
 impl FileDetector {
-    pub fn detect_file_type(path: &str) -> Result<FileType, io::Error> {
-        let bytes = fs::read(path)?;
-        let sample_size = std::cmp::min(bytes.len(), BINARY_CHECK_BYTES);
-        let sample = &bytes[..sample_size];
+    /// Classifies a sample of file content as text or binary.
+    ///
+    /// # Arguments
+    /// * `sample` - Leading bytes of the file
+    /// * `truncated` - Whether the file continues past the sample
+    pub fn classify_sample(sample: &[u8], truncated: bool) -> FileType {
+        let encoding = Self::detect_sample_encoding(sample, truncated);
+
+        if encoding.is_utf16() {
+            return FileType::Text;
+        }
 
         if sample.contains(&0) {
-            return Ok(FileType::Binary);
+            return FileType::Binary;
         }
 
-        if Self::is_valid_utf8(sample) || Self::is_valid_shift_jis(sample) {
-            return Ok(FileType::Text);
+        if !matches!(encoding, Encoding::Windows1252 | Encoding::Unknown) {
+            return FileType::Text;
         }
 
         if Self::calculate_non_printable_ratio(sample) > 0.25 {
-            Ok(FileType::Binary)
+            FileType::Binary
+        } else {
+            FileType::Text
+        }
+    }
+
+    /// Detects the encoding of a sample of file content.
+    ///
+    /// Byte order marks take priority, followed by UTF-16 byte patterns,
+    /// ISO-2022-JP escape sequences and UTF-8 validation. Shift-JIS and EUC-JP
+    /// candidates are compared by how much Japanese text they decode to, and
+    /// remaining 8-bit text falls back to Windows-1252.
+    pub fn detect_sample_encoding(sample: &[u8], truncated: bool) -> Encoding {
+        if let Some(encoding) = Self::detect_bom(sample) {
+            return encoding;
+        }
+
+        if let Some(encoding) = Self::detect_utf16_without_bom(sample) {
+            return encoding;
+        }
+
+        if sample.contains(&0) {
+            return Encoding::Unknown;
+        }
+
+        if Self::is_iso_2022_jp(sample) {
+            return Encoding::Iso2022Jp;
+        }
+
+        if Self::sample_is_utf8(sample, truncated) {
+            return Encoding::Utf8;
+        }
+
+        let mut best: Option<(Encoding, JapaneseScore)> = None;
+
+        if Self::sample_is_shift_jis(sample, truncated) {
+            best = Some((Encoding::ShiftJis, JapaneseScore::of(SHIFT_JIS, sample)));
+        }
+
+        if Self::sample_is_euc_jp(sample, truncated) {
+            let score = JapaneseScore::of(EUC_JP, sample);
+            if best.as_ref().is_none_or(|(_, current)| score.value() > current.value()) {
+                best = Some((Encoding::EucJp, score));
+            }
+        }
+
+        let latin1 = Self::is_latin1_text(sample);
+
+        match best {
+            Some((_, score)) if latin1 && score.kana == 0 => Encoding::Windows1252,
+            Some((encoding, _)) => encoding,
+            None if latin1 => Encoding::Windows1252,
+            None => Encoding::Unknown,
+        }
+    }
+
+    fn detect_bom(sample: &[u8]) -> Option<Encoding> {
+        if sample.starts_with(UTF8_BOM) {
+            Some(Encoding::Utf8Bom)
+        } else if sample.starts_with(&[0xFF, 0xFE]) {
+            Some(Encoding::Utf16Le)
+        } else if sample.starts_with(&[0xFE, 0xFF]) {
+            Some(Encoding::Utf16Be)
         } else {
-            Ok(FileType::Text)
+            None
         }
     }
 
-    pub fn detect_encoding(path: &str) -> Result<Encoding, io::Error> {
-        let bytes = fs::read(path)?;
-        let sample_size = std::cmp::min(bytes.len(), BINARY_CHECK_BYTES);
-        let sample = &bytes[..sample_size];
+    /// Recognizes BOM-less UTF-16 by NUL bytes concentrated on one side of each code unit.
+    fn detect_utf16_without_bom(sample: &[u8]) -> Option<Encoding> {
+        let units = sample.len() / 2;
+        if units < 2 {
+            return None;
+        }
 
-        if Self::is_valid_utf8(sample) {
-            Ok(Encoding::Utf8)
-        } else if Self::is_valid_shift_jis(sample) {
-            Ok(Encoding::ShiftJis)
+        let even_zeros = sample.iter().step_by(2).take(units).filter(|&&b| b == 0).count();
+        let odd_zeros = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+        let mostly = |count: usize| count * 10 >= units * 4;
+        let rarely = |count: usize| count * 20 <= units;
+
+        if mostly(odd_zeros) && rarely(even_zeros) {
+            Some(Encoding::Utf16Le)
+        } else if mostly(even_zeros) && rarely(odd_zeros) {
+            Some(Encoding::Utf16Be)
         } else {
-            Ok(Encoding::Unknown)
+            None
         }
     }
 
+    /// Checks for 7-bit content containing ISO-2022-JP escape sequences.
+    fn is_iso_2022_jp(sample: &[u8]) -> bool {
+        const ESCAPES: [&[u8]; 4] = [b"\x1b$B", b"\x1b$@", b"\x1b(J", b"\x1b(I"];
+
+        sample.is_ascii()
+            && ESCAPES.iter().any(|escape| sample.windows(escape.len()).any(|w| w == *escape))
+    }
+
+    /// Checks whether an 8-bit sample is plausible single-byte text.
+    fn is_latin1_text(sample: &[u8]) -> bool {
+        let control_count = sample.iter()
+            .filter(|&&b| b < 32 && !matches!(b, b'\n' | b'\r' | b'\t' | 0x0C))
+            .count();
+
+        control_count * 20 <= sample.len()
+    }
+
+    /// Checks a sample for UTF-8, tolerating a character cut off by truncation.
+    fn sample_is_utf8(sample: &[u8], truncated: bool) -> bool {
+        if !truncated {
+            return Self::is_valid_utf8(sample);
+        }
+
+        let continuation_bytes = sample.iter()
+            .rev()
+            .take(3)
+            .take_while(|&&b| (b & 0xC0) == 0x80)
+            .count();
+        let last_start = sample.len().saturating_sub(continuation_bytes + 1);
+
+        Self::is_valid_utf8(sample) || Self::is_valid_utf8(&sample[..last_start])
+    }
+
+    /// Checks a sample for Shift-JIS, tolerating a lead byte cut off by truncation.
+    fn sample_is_shift_jis(sample: &[u8], truncated: bool) -> bool {
+        if Self::is_valid_shift_jis(sample) {
+            return true;
+        }
+
+        truncated && !sample.is_empty() && Self::is_valid_shift_jis(&sample[..sample.len() - 1])
+    }
+
+    /// Checks a sample for EUC-JP, tolerating a character cut off by truncation.
+    fn sample_is_euc_jp(sample: &[u8], truncated: bool) -> bool {
+        if Self::is_valid_euc_jp(sample) {
+            return true;
+        }
+
+        truncated && (1..=2).any(|cut| {
+            sample.len() > cut && Self::is_valid_euc_jp(&sample[..sample.len() - cut])
+        })
+    }
+
     fn is_valid_utf8(bytes: &[u8]) -> bool {
         let mut i = 0;
         while i < bytes.len() {
@@ -110,6 +300,31 @@ impl FileDetector {
         true
     }
 
+    fn is_valid_euc_jp(bytes: &[u8]) -> bool {
+        let is_trail = |b: u8| (0xA1..=0xFE).contains(&b);
+        let mut i = 0;
+        while i < bytes.len() {
+            let byte = bytes[i];
+
+            if byte <= 0x7F {
+                i += 1;
+            } else if byte == 0x8E || is_trail(byte) {
+                if i + 1 >= bytes.len() || !is_trail(bytes[i + 1]) {
+                    return false;
+                }
+                i += 2;
+            } else if byte == 0x8F {
+                if i + 2 >= bytes.len() || !is_trail(bytes[i + 1]) || !is_trail(bytes[i + 2]) {
+                    return false;
+                }
+                i += 3;
+            } else {
+                return false;
+            }
+        }
+        true
+    }
+
     fn calculate_non_printable_ratio(bytes: &[u8]) -> f64 {
         if bytes.is_empty() {
             return 0.0;
@@ -127,6 +342,38 @@ impl FileDetector {
     }
 }
 
+/// Character statistics used to judge a candidate Japanese decoding.
+struct JapaneseScore {
+    /// Hiragana, katakana and CJK punctuation
+    kana: usize,
+    /// CJK unified ideographs
+    kanji: usize,
+    /// Half-width katakana and replacement characters
+    unlikely: usize,
+}
+
+impl JapaneseScore {
+    fn of(encoding: &'static encoding_rs::Encoding, sample: &[u8]) -> Self {
+        let (text, _) = encoding.decode_without_bom_handling(sample);
+        let mut score = Self { kana: 0, kanji: 0, unlikely: 0 };
+
+        for c in text.chars() {
+            match c as u32 {
+                0x3000..=0x30FF => score.kana += 1,
+                0x4E00..=0x9FFF => score.kanji += 1,
+                0xFF61..=0xFF9F | 0xFFFD => score.unlikely += 1,
+                _ => {}
+            }
+        }
+
+        score
+    }
+
+    fn value(&self) -> isize {
+        (self.kana * 2 + self.kanji) as isize - (self.unlikely * 2) as isize
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +387,62 @@ mod tests {
         assert!(!FileDetector::is_valid_utf8(invalid_utf8));
     }
 
+    #[test]
+    fn test_truncated_sample() {
+        let text = "日本語".as_bytes();
+        assert!(!FileDetector::sample_is_utf8(&text[..4], false));
+        assert!(FileDetector::sample_is_utf8(&text[..4], true));
+    }
+
+    #[test]
+    fn test_shift_jis_decoding() {
+        let sjis_bytes = &[0x93, 0xFA, 0x96, 0x7B, 0x8C, 0xEA, 0x0A];
+        assert!(!FileDetector::is_valid_utf8(sjis_bytes));
+        assert!(FileDetector::is_valid_shift_jis(sjis_bytes));
+        assert_eq!(Encoding::ShiftJis.decode(sjis_bytes).unwrap(), "日本語\n");
+        assert!(Encoding::Utf8.decode(sjis_bytes).is_err());
+    }
+
+    #[test]
+    fn test_bom_detection() {
+        let utf8 = b"\xEF\xBB\xBFhello";
+        assert_eq!(FileDetector::detect_sample_encoding(utf8, false), Encoding::Utf8Bom);
+        assert_eq!(Encoding::Utf8Bom.decode(utf8).unwrap(), "hello");
+
+        let utf16le = &[0xFF, 0xFE, b'h', 0, b'i', 0];
+        assert_eq!(FileDetector::detect_sample_encoding(utf16le, false), Encoding::Utf16Le);
+        assert_eq!(FileDetector::classify_sample(utf16le, false), FileType::Text);
+        assert_eq!(Encoding::Utf16Le.decode(utf16le).unwrap(), "hi");
+    }
+
+    #[test]
+    fn test_utf16_without_bom() {
+        let utf16be = &[0, b'f', 0, b'n', 0, b' ', 0, b'x'];
+        assert_eq!(FileDetector::detect_sample_encoding(utf16be, false), Encoding::Utf16Be);
+        assert_eq!(Encoding::Utf16Be.decode(utf16be).unwrap(), "fn x");
+    }
+
+    #[test]
+    fn test_japanese_detection() {
+        let (euc, _, _) = EUC_JP.encode("これは日本語です。\n");
+        assert_eq!(FileDetector::detect_sample_encoding(&euc, false), Encoding::EucJp);
+
+        let (sjis, _, _) = SHIFT_JIS.encode("これは日本語です。\n");
+        assert_eq!(FileDetector::detect_sample_encoding(&sjis, false), Encoding::ShiftJis);
+
+        let iso = b"\x1b$B$3$s$K$A$O\x1b(B\n";
+        assert_eq!(FileDetector::detect_sample_encoding(iso, false), Encoding::Iso2022Jp);
+        assert_eq!(Encoding::Iso2022Jp.decode(iso).unwrap(), "こんにちは\n");
+    }
+
+    #[test]
+    fn test_latin1_detection() {
+        let latin1 = b"caf\xE9 na\xEFve r\xE9sum\xE9\n";
+        assert_eq!(FileDetector::detect_sample_encoding(latin1, false), Encoding::Windows1252);
+        assert_eq!(FileDetector::classify_sample(latin1, false), FileType::Text);
+        assert_eq!(Encoding::Windows1252.decode(latin1).unwrap(), "café naïve résumé\n");
+    }
+
     #[test]
     fn test_binary_detection() {
         let text_data = "Hello world".as_bytes();
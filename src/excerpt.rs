@@ -0,0 +1,80 @@
+//! Head and tail excerpts of long files.
+//!
+//! Long files can be shortened to their first and last lines, with a marker
+//! in between that states how many lines were left out.
+
+use crate::units::format_count;
+
+/// How many leading and trailing lines to keep from long files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineExcerpt {
+    /// Lines kept from the beginning
+    pub head: usize,
+    /// Lines kept from the end
+    pub tail: usize,
+}
+
+impl LineExcerpt {
+    /// Creates an excerpt that keeps `max_lines` lines, split between head and tail.
+    pub fn from_max_lines(max_lines: usize) -> Self {
+        Self {
+            head: max_lines.div_ceil(2),
+            tail: max_lines / 2,
+        }
+    }
+
+    /// Shortens text that has more lines than the excerpt keeps.
+    ///
+    /// Returns the shortened text and the number of omitted lines, or `None`
+    /// if the text is short enough to show whole.
+    pub fn apply(&self, text: &str) -> Option<(String, usize)> {
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        if lines.len() <= self.head + self.tail {
+            return None;
+        }
+
+        let omitted = lines.len() - self.head - self.tail;
+        let head = lines[..self.head].concat();
+        let tail = lines[lines.len() - self.tail..].concat();
+
+        Some((format!("{}… {} lines omitted …\n{}", head, format_count(omitted), tail), omitted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(count: usize) -> String {
+        (1..=count).map(|n| format!("line {}\n", n)).collect()
+    }
+
+    #[test]
+    fn test_short_text_is_unchanged() {
+        let excerpt = LineExcerpt { head: 3, tail: 2 };
+        assert_eq!(excerpt.apply(&numbered(5)), None);
+    }
+
+    #[test]
+    fn test_head_and_tail() {
+        let excerpt = LineExcerpt { head: 2, tail: 1 };
+        let (text, omitted) = excerpt.apply(&numbered(5000)).unwrap();
+
+        assert_eq!(omitted, 4997);
+        assert_eq!(text, "line 1\nline 2\n… 4,997 lines omitted …\nline 5000\n");
+    }
+
+    #[test]
+    fn test_head_only() {
+        let excerpt = LineExcerpt { head: 1, tail: 0 };
+        let (text, omitted) = excerpt.apply("a\nb\nc").unwrap();
+
+        assert_eq!(omitted, 2);
+        assert_eq!(text, "a\n… 2 lines omitted …\n");
+    }
+
+    #[test]
+    fn test_from_max_lines() {
+        assert_eq!(LineExcerpt::from_max_lines(5), LineExcerpt { head: 3, tail: 2 });
+    }
+}
@@ -1,48 +1,107 @@
 //! File filtering logic.
 //!
 //! This module provides filtering capabilities for files and directories
-//! based on extensions, ignore patterns, and hidden file visibility.
+//! based on extensions, include and ignore patterns, and hidden file visibility.
+//! Ignore patterns use gitignore syntax and are matched against paths relative
+//! to the scanned directory.
 
 use std::path::Path;
-use glob::Pattern;
+use glob::{MatchOptions, Pattern};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::config::ConfigError;
 
 /// Manages file filtering based on various criteria.
 #[derive(Debug, Clone)]
 pub struct FileFilter {
     /// File extensions to include (empty = all)
     extensions: Vec<String>,
-    /// Glob patterns to ignore
+    /// Glob patterns for relative paths to include (empty = all)
+    include_patterns: Vec<Pattern>,
+    /// Ignore patterns as given, for display
     ignore_patterns: Vec<String>,
+    /// Compiled ignore patterns
+    ignore_matcher: Gitignore,
     /// Whether to show hidden files
     show_hidden: bool,
 }
 
 impl FileFilter {
     /// Creates a new FileFilter with the specified criteria.
-    pub fn new(extensions: Vec<String>, ignore_patterns: Vec<String>, show_hidden: bool) -> Self {
-        Self {
+    ///
+    /// Returns `Err(ConfigError::InvalidPattern)` if an include or ignore pattern is malformed.
+    pub fn new(
+        extensions: Vec<String>,
+        include_patterns: &[String],
+        ignore_patterns: Vec<String>,
+        show_hidden: bool,
+    ) -> Result<Self, ConfigError> {
+        let include_patterns = include_patterns
+            .iter()
+            .map(|p| Pattern::new(p).map_err(|e| ConfigError::InvalidPattern(p.clone(), e.msg.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut builder = GitignoreBuilder::new("");
+        for pattern in &ignore_patterns {
+            builder.add_line(None, pattern).map_err(|e| pattern_error(pattern, e))?;
+        }
+        let ignore_matcher = builder.build().map_err(|e| pattern_error("", e))?;
+
+        Ok(Self {
             extensions,
+            include_patterns,
             ignore_patterns,
+            ignore_matcher,
             show_hidden,
-        }
+        })
     }
 
     /// Determines if a path should be processed.
     ///
     /// # Arguments
-    /// * `path` - The file or directory path to check
+    /// * `relative` - The path relative to the input directory, using `/` separators
+    /// * `is_dir` - Whether the path refers to a directory
     ///
     /// Returns `true` if the path passes all filters, `false` otherwise.
-    pub fn should_process(&self, path: &str) -> bool {
-        if self.should_ignore(path) {
+    /// Directories are only checked against ignore and hidden file rules.
+    pub fn should_process(&self, relative: &str, is_dir: bool) -> bool {
+        if self.should_ignore(relative, is_dir) {
             return false;
         }
 
-        if Path::new(path).is_dir() {
+        if is_dir {
             return true;
         }
 
-        self.matches_extension(path)
+        self.matches_extension(relative) && self.matches_include(relative)
+    }
+
+    /// Determines if a file given directly as an input should be processed.
+    ///
+    /// # Arguments
+    /// * `name` - The file name
+    ///
+    /// Include patterns describe paths inside an input directory, so they do
+    /// not apply to a file named on its own; extensions, ignore patterns and
+    /// hidden file rules still do.
+    pub fn should_process_input(&self, name: &str) -> bool {
+        !self.should_ignore(name, false) && self.matches_extension(name)
+    }
+
+    /// Checks if a relative path matches the include patterns.
+    ///
+    /// `*` stays within one path component while `**` spans directories.
+    fn matches_include(&self, relative: &str) -> bool {
+        if self.include_patterns.is_empty() {
+            return true;
+        }
+
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::default()
+        };
+        self.include_patterns.iter().any(|p| p.matches_with(relative, options))
     }
 
     /// Checks if a file matches the extension filter.
@@ -62,38 +121,37 @@ impl FileFilter {
     }
 
     /// Checks if a path should be ignored based on patterns and hidden file rules.
-    fn should_ignore(&self, path: &str) -> bool {
-        let path_obj = Path::new(path);
+    ///
+    /// # Arguments
+    /// * `relative` - The path relative to the input directory
+    /// * `is_dir` - Whether the path refers to a directory
+    ///
+    /// A path is ignored if it or any of its parent directories is excluded,
+    /// so `foo/` and `/docs` also exclude everything below them.
+    fn should_ignore(&self, relative: &str, is_dir: bool) -> bool {
+        let components: Vec<&str> = relative
+            .split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .collect();
+        let mut current = String::new();
 
-        for component in path_obj.components() {
-            if let std::path::Component::Normal(name) = component {
-                let name_str = name.to_string_lossy();
+        for (index, component) in components.iter().enumerate() {
+            if !self.show_hidden && component.starts_with('.') {
+                return true;
+            }
 
-                if self.matches_ignore_pattern(&name_str) {
-                    return true;
-                }
+            if !current.is_empty() {
+                current.push('/');
+            }
+            current.push_str(component);
 
-                if !self.show_hidden && name_str.starts_with('.') {
-                    return true;
-                }
+            let component_is_dir = index + 1 < components.len() || is_dir;
+            if let Match::Ignore(_) = self.ignore_matcher.matched(Path::new(&current), component_is_dir) {
+                return true;
             }
         }
 
-        let basename = path_obj
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or(path);
-
-        self.matches_ignore_pattern(path) || self.matches_ignore_pattern(basename)
-    }
-
-    /// Tests if text matches any ignore pattern.
-    fn matches_ignore_pattern(&self, text: &str) -> bool {
-        self.ignore_patterns.iter().any(|pattern| {
-            Pattern::new(pattern)
-                .map(|glob| glob.matches(text))
-                .unwrap_or(false)
-        })
+        false
     }
 
     /// Returns a human-readable description of active filters.
@@ -104,6 +162,11 @@ impl FileFilter {
             parts.push(format!("extensions: {}", self.extensions.join(", ")));
         }
 
+        if !self.include_patterns.is_empty() {
+            let patterns: Vec<&str> = self.include_patterns.iter().map(|p| p.as_str()).collect();
+            parts.push(format!("including: {}", patterns.join(", ")));
+        }
+
         if !self.ignore_patterns.is_empty() {
             parts.push(format!("ignoring: {}", self.ignore_patterns.join(", ")));
         }
@@ -121,7 +184,10 @@ impl FileFilter {
 
     /// Returns true if any filters are active.
     pub fn has_filters(&self) -> bool {
-        !self.extensions.is_empty() || !self.ignore_patterns.is_empty() || !self.show_hidden
+        !self.extensions.is_empty()
+            || !self.include_patterns.is_empty()
+            || !self.ignore_patterns.is_empty()
+            || !self.show_hidden
     }
 
     /// Returns the list of active extension filters.
@@ -130,6 +196,15 @@ impl FileFilter {
     }
 }
 
+/// Converts a pattern compilation error into a configuration error.
+fn pattern_error(pattern: &str, error: ignore::Error) -> ConfigError {
+    let reason = match error {
+        ignore::Error::Glob { err, .. } => err,
+        other => other.to_string(),
+    };
+    ConfigError::InvalidPattern(pattern.to_string(), reason)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,9 +213,10 @@ mod tests {
     fn test_extension_filtering() {
         let filter = FileFilter::new(
             vec!["rs".to_string(), "toml".to_string()],
+            &[],
             vec![],
             false,
-        );
+        ).unwrap();
 
         assert!(filter.matches_extension("main.rs"));
         assert!(filter.matches_extension("Cargo.toml"));
@@ -153,36 +229,93 @@ mod tests {
     fn test_ignore_patterns() {
         let filter = FileFilter::new(
             vec![],
+            &[],
             vec!["*.tmp".to_string(), "target".to_string()],
             false,
-        );
+        ).unwrap();
+
+        assert!(filter.should_ignore("file.tmp", false));
+        assert!(filter.should_ignore("src/file.tmp", false));
+        assert!(filter.should_ignore("target/debug/build", false));
+        assert!(filter.should_ignore("crates/core/target/debug", true));
+        assert!(filter.should_ignore(".hidden_file", false));
+        assert!(!filter.should_ignore("main.rs", false));
+    }
+
+    #[test]
+    fn test_path_ignore_patterns() {
+        let filter = FileFilter::new(
+            vec![],
+            &[],
+            vec![
+                "src/generated/**".to_string(),
+                "/docs".to_string(),
+                "build/".to_string(),
+                "**/fixtures/*.json".to_string(),
+            ],
+            false,
+        ).unwrap();
+
+        assert!(filter.should_ignore("src/generated/api/client.rs", false));
+        assert!(!filter.should_ignore("lib/src/generated/client.rs", false));
+        assert!(filter.should_ignore("docs/index.md", false));
+        assert!(!filter.should_ignore("src/docs/index.md", false));
+        assert!(filter.should_ignore("build/out.txt", false));
+        assert!(filter.should_ignore("web/build/app.js", false));
+        assert!(!filter.should_ignore("build", false));
+        assert!(filter.should_ignore("tests/fixtures/data.json", false));
+        assert!(!filter.should_ignore("tests/fixtures/data.yaml", false));
 
-        assert!(filter.should_ignore("file.tmp"));
-        assert!(filter.should_ignore("target/debug/build"));
-        assert!(filter.should_ignore(".hidden_file"));
-        assert!(!filter.should_ignore("main.rs"));
+        match FileFilter::new(vec![], &[], vec!["src/{a,b".to_string()], false) {
+            Err(ConfigError::InvalidPattern(pattern, _)) => assert_eq!(pattern, "src/{a,b"),
+            other => panic!("expected invalid pattern, got {:?}", other),
+        }
     }
 
     #[test]
     fn test_combined_filters() {
         let filter = FileFilter::new(
             vec!["rs".to_string()],
+            &[],
             vec!["*_test.rs".to_string()],
             false,
-        );
+        ).unwrap();
+
+        assert!(filter.should_process("main.rs", false));
+        assert!(!filter.should_process("main_test.rs", false));
+        assert!(!filter.should_process("README.md", false));
+        assert!(!filter.should_process(".hidden.rs", false));
+        assert!(filter.should_process("docs", true));
+    }
+
+    #[test]
+    fn test_include_patterns() {
+        let filter = FileFilter::new(
+            vec![],
+            &["src/**/*.rs".to_string(), "Cargo.toml".to_string()],
+            vec![],
+            false,
+        ).unwrap();
+
+        assert!(filter.matches_include("src/main.rs"));
+        assert!(filter.matches_include("src/io/mod.rs"));
+        assert!(filter.matches_include("Cargo.toml"));
+        assert!(!filter.matches_include("crates/core/Cargo.toml"));
+        assert!(!filter.matches_include("tests/src/main.rs"));
+        assert!(!filter.matches_include("src/notes.md"));
+        assert!(!filter.should_process("main.rs", false));
+        assert!(filter.should_process_input("main.rs"));
 
-        assert!(filter.should_process("main.rs"));
-        assert!(!filter.should_process("main_test.rs"));
-        assert!(!filter.should_process("README.md"));
-        assert!(!filter.should_process(".hidden.rs"));
+        assert!(FileFilter::new(vec![], &["src/[".to_string()], vec![], false).is_err());
     }
 
     #[test]
     fn test_show_hidden() {
-        let filter_hide = FileFilter::new(vec![], vec![], false);
-        let filter_show = FileFilter::new(vec![], vec![], true);
+        let filter_hide = FileFilter::new(vec![], &[], vec![], false).unwrap();
+        let filter_show = FileFilter::new(vec![], &[], vec![], true).unwrap();
 
-        assert!(filter_hide.should_ignore(".gitignore"));
-        assert!(!filter_show.should_ignore(".gitignore"));
+        assert!(filter_hide.should_ignore(".gitignore", false));
+        assert!(filter_hide.should_ignore(".github/workflows/ci.yml", false));
+        assert!(!filter_show.should_ignore(".gitignore", false));
     }
 }
\ No newline at end of file
@@ -0,0 +1,188 @@
+//! Git-style ignore file support.
+//!
+//! This module loads `.gitignore` and `.ignore` files from every directory
+//! level of a scanned tree, together with `.git/info/exclude` and the user's
+//! global excludes file, and evaluates paths against them using gitignore
+//! semantics (negation, anchoring and directory-only patterns).
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+/// Per-directory ignore files, in increasing order of precedence.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Ignore rules for a single scanned directory tree.
+#[derive(Debug)]
+pub struct IgnoreRules {
+    /// Canonical root of the scan
+    root: PathBuf,
+    /// Top-most directory whose ignore files apply (repository root or scan root)
+    top: PathBuf,
+    /// Rules from `.git/info/exclude`
+    exclude: Gitignore,
+    /// Rules from the global git excludes file
+    global: Gitignore,
+    /// Lazily loaded per-directory matchers
+    cache: RefCell<HashMap<PathBuf, Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// Creates ignore rules for the tree rooted at `root`.
+    ///
+    /// If `root` lies inside a git repository, ignore files between the
+    /// repository root and `root` are honored as well.
+    pub fn new(root: &Path) -> Self {
+        let root = root.to_path_buf();
+        let repo_root = find_repo_root(&root);
+        let top = repo_root.clone().unwrap_or_else(|| root.clone());
+
+        let exclude = match &repo_root {
+            Some(repo) => {
+                let mut builder = GitignoreBuilder::new(repo);
+                builder.add(repo.join(".git").join("info").join("exclude"));
+                builder.build().unwrap_or_else(|_| Gitignore::empty())
+            }
+            None => Gitignore::empty(),
+        };
+
+        let (global, _) = Gitignore::global();
+
+        Self {
+            root,
+            top,
+            exclude,
+            global,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Determines whether a path is ignored.
+    ///
+    /// # Arguments
+    /// * `relative` - Path relative to the scan root
+    /// * `is_dir` - Whether the path refers to a directory
+    ///
+    /// A path is ignored if it or any of its parent directories is excluded.
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let components: Vec<_> = relative.components().collect();
+        let mut current = self.root.clone();
+
+        for (index, component) in components.iter().enumerate() {
+            current.push(component);
+            let component_is_dir = index + 1 < components.len() || is_dir;
+            if self.matches(&current, component_is_dir) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Evaluates a single absolute path against every applicable rule set.
+    ///
+    /// Deeper ignore files take precedence over shallower ones, which in turn
+    /// take precedence over `.git/info/exclude` and the global excludes file.
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        let mut dir = path.parent();
+
+        while let Some(current) = dir {
+            if !current.starts_with(&self.top) {
+                break;
+            }
+
+            if let Ok(relative) = path.strip_prefix(current) {
+                let mut cache = self.cache.borrow_mut();
+                let matcher = cache
+                    .entry(current.to_path_buf())
+                    .or_insert_with(|| load_directory_rules(current));
+
+                match matcher.matched(relative, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+
+            dir = current.parent();
+        }
+
+        let relative = path.strip_prefix(&self.top).unwrap_or(path);
+        for matcher in [&self.exclude, &self.global] {
+            match matcher.matched(relative, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        false
+    }
+}
+
+/// Builds a matcher from the ignore files found directly in `dir`.
+fn load_directory_rules(dir: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+
+    for name in IGNORE_FILES {
+        let file = dir.join(name);
+        if file.is_file() {
+            builder.add(file);
+        }
+    }
+
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Finds the root of the git repository containing `path`, if any.
+fn find_repo_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    fn setup(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        fs::create_dir_all(dir.join("src/generated")).unwrap();
+        fs::create_dir_all(dir.join("target/debug")).unwrap();
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_nested_ignore_files() {
+        let dir = setup("nested");
+        fs::write(dir.join(".gitignore"), "target/\n*.log\n/docs\n").unwrap();
+        fs::write(dir.join("src/.gitignore"), "generated/\n!keep.log\n").unwrap();
+
+        let rules = IgnoreRules::new(&dir);
+
+        assert!(rules.is_ignored(Path::new("target/debug/app"), false));
+        assert!(rules.is_ignored(Path::new("build.log"), false));
+        assert!(rules.is_ignored(Path::new("docs/index.md"), false));
+        assert!(rules.is_ignored(Path::new("src/generated/mod.rs"), false));
+        assert!(!rules.is_ignored(Path::new("src/keep.log"), false));
+        assert!(!rules.is_ignored(Path::new("src/docs/index.md"), false));
+        assert!(!rules.is_ignored(Path::new("src/main.rs"), false));
+    }
+
+    #[test]
+    fn test_directory_only_patterns() {
+        let dir = setup("dironly");
+        fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+
+        let rules = IgnoreRules::new(&dir);
+
+        assert!(rules.is_ignored(Path::new("target"), true));
+        assert!(!rules.is_ignored(Path::new("src/target"), false));
+    }
+}
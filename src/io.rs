@@ -1,25 +1,52 @@
 //! File system operations and directory traversal utilities.
 
+use std::collections::VecDeque;
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-/// Reads the entire contents of a file into a string.
-pub fn read_file_content(filename: &str) -> Result<String, io::Error> {
-    fs::read_to_string(filename)
-}
+use clap::ValueEnum;
 
 /// Returns a sorted list of directory entries.
 ///
 /// Directories are listed first, followed by files, both sorted alphabetically.
-pub fn read_directory_entries(dir_path: &str) -> Result<Vec<DirectoryEntry>, io::Error> {
+/// Entries whose metadata cannot be read are left out and reported in `errors`.
+///
+/// # Arguments
+/// * `dir_path` - The directory to list
+/// * `follow_symlinks` - Whether symlinks take the type and size of their targets
+/// * `errors` - Receives errors for individual entries
+pub fn read_directory_entries(
+    dir_path: &str,
+    follow_symlinks: bool,
+    errors: &mut Vec<WalkError>,
+) -> Result<Vec<DirectoryEntry>, io::Error> {
     let mut entries = Vec::new();
     let dir = fs::read_dir(dir_path)?;
 
     for entry in dir {
-        let entry = entry?;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                errors.push(WalkError { path: dir_path.to_string(), error });
+                continue;
+            }
+        };
         let path = entry.path();
-        let metadata = entry.metadata()?;
+        let is_symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+        let metadata = if is_symlink && follow_symlinks {
+            fs::metadata(&path)
+        } else {
+            entry.metadata()
+        };
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                errors.push(WalkError { path: path.to_string_lossy().to_string(), error });
+                continue;
+            }
+        };
 
         let entry_info = DirectoryEntry {
             name: path.file_name()
@@ -30,6 +57,9 @@ pub fn read_directory_entries(dir_path: &str) -> Result<Vec<DirectoryEntry>, io:
             is_directory: metadata.is_dir(),
             is_file: metadata.is_file(),
             size: if metadata.is_file() { Some(metadata.len()) } else { None },
+            symlink: if is_symlink { fs::read_link(&path).ok() } else { None },
+            id: file_id(&metadata),
+            refused: None,
         };
 
         entries.push(entry_info);
@@ -63,38 +93,240 @@ pub fn check_path_type(path: &str) -> Result<PathType, io::Error> {
     }
 }
 
-/// Recursively walks a directory and returns all file paths.
-///
-/// # Arguments
-/// * `dir_path` - The directory to traverse
-/// * `max_depth` - Maximum recursion depth (None for unlimited)
-pub fn walk_directory(dir_path: &str, max_depth: Option<usize>) -> Result<Vec<String>, io::Error> {
-    let mut files = Vec::new();
-    walk_directory_recursive(dir_path, max_depth.unwrap_or(100), 0, &mut files)?;
-    Ok(files)
+/// A directory or entry that could not be read during a walk.
+#[derive(Debug)]
+pub struct WalkError {
+    /// The path that failed
+    pub path: String,
+    /// The underlying error
+    pub error: io::Error,
+}
+
+impl std::fmt::Display for WalkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+impl std::error::Error for WalkError {}
+
+/// Which symlinks a walk follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FollowSymlinks {
+    /// Follow every symlink
+    All,
+    /// Follow only symlinks whose targets lie inside the walked directory
+    WithinRoot,
 }
 
-fn walk_directory_recursive(
-    current_path: &str,
+/// Depth-first iterator over the files below a directory.
+///
+/// Entries are yielded as the walk proceeds, directories first and then
+/// files at each level, each group sorted alphabetically. Only one sorted
+/// listing per directory level is held in memory at a time.
+///
+/// Unreadable directories and entries are yielded as errors and skipped;
+/// the walk continues with the rest of the tree. Symlinks that are not
+/// followed are yielded with the reason in `refused`, so they can be shown
+/// without being read; a followed link back to a directory being walked is
+/// refused rather than looping until the depth limit.
+pub struct Walker<'a> {
+    /// Pending entries of each directory being walked, innermost last
+    stack: Vec<std::vec::IntoIter<DirectoryEntry>>,
+    /// Device and inode of each directory on the stack
+    ancestors: Vec<Option<(u64, u64)>>,
+    /// Errors waiting to be yielded
+    errors: VecDeque<WalkError>,
+    /// Directory to read on the first call to `next`
+    root: Option<String>,
+    /// Maximum recursion depth
     max_depth: usize,
-    current_depth: usize,
-    files: &mut Vec<String>
-) -> Result<(), io::Error> {
-    if current_depth >= max_depth {
-        return Ok(());
+    /// Returns whether a file or directory entry should be kept
+    filter: &'a dyn Fn(&DirectoryEntry) -> bool,
+    /// Which symlinks to follow, if any
+    follow_symlinks: Option<FollowSymlinks>,
+    /// Canonical walk root, used to contain symlinks
+    canonical_root: Option<PathBuf>,
+    /// Followed directory symlinks, with their targets
+    linked_directories: Vec<(String, PathBuf)>,
+    /// Symlinks that were not followed, with the reason
+    refused_links: Vec<(String, String)>,
+    /// Number of directories read
+    directories: usize,
+    /// Number of directories skipped without being read
+    pruned: usize,
+    /// Time spent reading directories
+    read_time: Duration,
+}
+
+impl<'a> Walker<'a> {
+    /// Creates a walker over the files below `dir_path` that pass `filter`.
+    ///
+    /// Directories rejected by `filter` are pruned: their contents are never read.
+    ///
+    /// # Arguments
+    /// * `dir_path` - The directory to traverse
+    /// * `max_depth` - Maximum recursion depth (None for unlimited)
+    /// * `follow_symlinks` - Which symlinks to follow (None to skip them)
+    /// * `filter` - Returns whether a file or directory entry should be kept
+    pub fn new(
+        dir_path: &str,
+        max_depth: Option<usize>,
+        follow_symlinks: Option<FollowSymlinks>,
+        filter: &'a dyn Fn(&DirectoryEntry) -> bool,
+    ) -> Self {
+        let canonical_root = match follow_symlinks {
+            Some(FollowSymlinks::WithinRoot) => fs::canonicalize(dir_path).ok(),
+            _ => None,
+        };
+
+        Self {
+            stack: Vec::new(),
+            ancestors: Vec::new(),
+            errors: VecDeque::new(),
+            root: Some(dir_path.to_string()),
+            max_depth: max_depth.unwrap_or(100),
+            filter,
+            follow_symlinks,
+            canonical_root,
+            linked_directories: Vec::new(),
+            refused_links: Vec::new(),
+            directories: 0,
+            pruned: 0,
+            read_time: Duration::ZERO,
+        }
+    }
+
+    /// Returns the number of directories read so far.
+    pub fn directories(&self) -> usize {
+        self.directories
+    }
+
+    /// Returns the number of directories skipped so far without being read.
+    pub fn pruned(&self) -> usize {
+        self.pruned
+    }
+
+    /// Returns a lower bound on the time pruning saved so far.
+    ///
+    /// Each skipped directory is counted as one directory taking the average
+    /// read time. Whatever lies below a skipped directory is never seen, so a
+    /// pruned tree such as `node_modules` may have saved far more.
+    pub fn pruning_saved(&self) -> Duration {
+        if self.directories == 0 {
+            return Duration::ZERO;
+        }
+        self.read_time.mul_f64(self.pruned as f64 / self.directories as f64)
+    }
+
+    /// Returns the directory symlinks followed so far, with their targets.
+    pub fn linked_directories(&self) -> &[(String, PathBuf)] {
+        &self.linked_directories
+    }
+
+    /// Returns the symlinks not followed so far, with the reason.
+    pub fn refused_links(&self) -> &[(String, String)] {
+        &self.refused_links
+    }
+
+    /// Reads a directory and schedules its entries, queueing any errors.
+    fn descend(&mut self, dir_path: &str, id: Option<(u64, u64)>) {
+        let mut errors = Vec::new();
+
+        let started = Instant::now();
+        let entries = read_directory_entries(dir_path, self.follow_symlinks.is_some(), &mut errors);
+        self.read_time += started.elapsed();
+
+        match entries {
+            Ok(entries) => {
+                self.directories += 1;
+                self.stack.push(entries.into_iter());
+                self.ancestors.push(id);
+            }
+            Err(error) => errors.push(WalkError { path: dir_path.to_string(), error }),
+        }
+
+        self.errors.extend(errors);
     }
 
-    let entries = read_directory_entries(current_path)?;
+    /// Returns why a symlink must not be followed, if it must not.
+    fn refuse_link(&self, entry: &DirectoryEntry) -> Option<String> {
+        if let Some(root) = &self.canonical_root {
+            let inside = fs::canonicalize(&entry.path).is_ok_and(|target| target.starts_with(root));
+            if !inside {
+                return Some("points outside the input directory".to_string());
+            }
+        }
 
-    for entry in entries {
-        if entry.is_file {
-            files.push(entry.path.clone());
-        } else if entry.is_directory {
-            walk_directory_recursive(&entry.path, max_depth, current_depth + 1, files)?;
+        if entry.is_directory && entry.id.is_some() && self.ancestors.contains(&entry.id) {
+            return Some("points to a directory that contains it".to_string());
         }
+
+        None
     }
+}
+
+impl Iterator for Walker<'_> {
+    type Item = Result<DirectoryEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if self.max_depth == 0 {
+                return None;
+            }
+            let id = fs::metadata(&root).ok().and_then(|m| file_id(&m));
+            self.descend(&root, id);
+        }
+
+        loop {
+            if let Some(error) = self.errors.pop_front() {
+                return Some(Err(error));
+            }
+
+            let entry = match self.stack.last_mut()?.next() {
+                Some(entry) => entry,
+                None => {
+                    self.stack.pop();
+                    self.ancestors.pop();
+                    continue;
+                }
+            };
+
+            let unfollowed = entry.symlink.is_some() && self.follow_symlinks.is_none();
+            if !(entry.is_file || entry.is_directory || unfollowed) {
+                continue;
+            }
+
+            if !(self.filter)(&entry) {
+                if entry.is_directory {
+                    self.pruned += 1;
+                }
+                continue;
+            }
 
-    Ok(())
+            if entry.symlink.is_some() {
+                let reason = match self.follow_symlinks {
+                    None => Some("following symlinks is off".to_string()),
+                    Some(_) => self.refuse_link(&entry),
+                };
+                if let Some(reason) = reason {
+                    self.refused_links.push((entry.path.clone(), reason.clone()));
+                    return Some(Ok(DirectoryEntry { refused: Some(reason), ..entry }));
+                }
+            }
+
+            if entry.is_file {
+                return Some(Ok(entry));
+            }
+
+            if self.stack.len() < self.max_depth {
+                if let Some(target) = &entry.symlink {
+                    self.linked_directories.push((entry.path.clone(), target.clone()));
+                }
+                self.descend(&entry.path, entry.id);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -104,6 +336,25 @@ pub struct DirectoryEntry {
     pub is_directory: bool,
     pub is_file: bool,
     pub size: Option<u64>,
+    /// Target of the entry if it is a symlink
+    pub symlink: Option<PathBuf>,
+    /// Device and inode of the entry (or its target), where available
+    pub id: Option<(u64, u64)>,
+    /// Why the entry, a symlink, was not followed
+    pub refused: Option<String>,
+}
+
+/// Returns the device and inode numbers identifying a file.
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Returns the device and inode numbers identifying a file.
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
 }
 
 #[derive(Debug, PartialEq)]
@@ -111,4 +362,94 @@ pub enum PathType {
     File,
     Directory,
     Other,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_walker() {
+        let dir = TempDir::new("walker");
+        fs::create_dir_all(dir.join("b/deep")).unwrap();
+        fs::create_dir_all(dir.join("node_modules/pkg")).unwrap();
+        for file in ["a.txt", "b/c.txt", "b/deep/d.txt", "node_modules/pkg/e.js"] {
+            fs::write(dir.join(file), "x").unwrap();
+        }
+
+        let root = dir.to_string_lossy().to_string();
+        let keep = |entry: &DirectoryEntry| entry.name != "node_modules";
+        let names = |walker: Walker| -> Vec<String> {
+            walker.map(|entry| entry.unwrap().name).collect()
+        };
+
+        assert_eq!(names(Walker::new(&root, None, None, &keep)), vec!["d.txt", "c.txt", "a.txt"]);
+        assert_eq!(names(Walker::new(&root, Some(2), None, &keep)), vec!["c.txt", "a.txt"]);
+
+        let mut walker = Walker::new(&root, None, None, &keep);
+        walker.by_ref().for_each(drop);
+        assert_eq!((walker.directories(), walker.pruned()), (3, 1));
+        assert_eq!(walker.pruning_saved(), walker.read_time.mul_f64(1.0 / 3.0));
+
+        let missing = dir.join("missing").to_string_lossy().to_string();
+        let mut walker = Walker::new(&missing, None, None, &keep);
+        assert!(matches!(walker.next(), Some(Err(WalkError { .. }))));
+        assert!(walker.next().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walker_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let base = TempDir::new("symlinks");
+        let dir = base.join("root");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(base.join("outside")).unwrap();
+        fs::write(dir.join("src/main.rs"), "x").unwrap();
+        fs::write(base.join("outside/secret.txt"), "x").unwrap();
+        symlink("main.rs", dir.join("src/alias.rs")).unwrap();
+        symlink("..", dir.join("src/loop")).unwrap();
+        symlink("../outside", dir.join("external")).unwrap();
+
+        let root = dir.to_string_lossy().to_string();
+        let keep = |_: &DirectoryEntry| true;
+        let paths = |walker: &mut Walker| -> Vec<String> {
+            walker
+                .map(|entry| {
+                    let entry = entry.unwrap();
+                    let path = relative(&entry.path, &root);
+                    if entry.refused.is_some() { format!("{} (refused)", path) } else { path }
+                })
+                .collect()
+        };
+
+        let mut walker = Walker::new(&root, None, None, &keep);
+        assert_eq!(
+            paths(&mut walker),
+            vec!["src/alias.rs (refused)", "src/loop (refused)", "src/main.rs", "external (refused)"],
+        );
+        assert_eq!(walker.refused_links().len(), 3);
+
+        let mut walker = Walker::new(&root, None, Some(FollowSymlinks::All), &keep);
+        assert_eq!(
+            paths(&mut walker),
+            vec!["external/secret.txt", "src/loop (refused)", "src/alias.rs", "src/main.rs"],
+        );
+        assert_eq!(walker.linked_directories().len(), 1);
+        assert_eq!(walker.refused_links().len(), 1);
+        assert!(walker.refused_links()[0].0.ends_with("loop"));
+
+        let mut walker = Walker::new(&root, None, Some(FollowSymlinks::WithinRoot), &keep);
+        assert_eq!(
+            paths(&mut walker),
+            vec!["external (refused)", "src/loop (refused)", "src/alias.rs", "src/main.rs"],
+        );
+        assert_eq!(walker.refused_links().len(), 2);
+    }
+
+    fn relative(path: &str, root: &str) -> String {
+        path.strip_prefix(root).unwrap_or(path).trim_start_matches('/').to_string()
+    }
+}
@@ -0,0 +1,146 @@
+//! Programming language identification.
+//!
+//! Maps file names and extensions to the language tags commonly understood
+//! by Markdown code fences.
+
+use std::path::Path;
+
+/// Well-known file names without a distinguishing extension.
+const FILE_NAMES: &[(&str, &str)] = &[
+    ("Dockerfile", "Dockerfile"),
+    ("Containerfile", "Dockerfile"),
+    ("Makefile", "makefile"),
+    ("GNUmakefile", "makefile"),
+    ("CMakeLists.txt", "cmake"),
+    ("Jenkinsfile", "groovy"),
+    ("Rakefile", "ruby"),
+    ("Gemfile", "ruby"),
+    ("Vagrantfile", "ruby"),
+    ("BUILD", "python"),
+    ("WORKSPACE", "python"),
+    (".bashrc", "bash"),
+    (".zshrc", "zsh"),
+    (".gitignore", "gitignore"),
+    (".dockerignore", "gitignore"),
+    (".editorconfig", "ini"),
+];
+
+/// File extensions, matched case-insensitively.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("toml", "toml"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cc", "cpp"),
+    ("cpp", "cpp"),
+    ("cxx", "cpp"),
+    ("hpp", "cpp"),
+    ("hh", "cpp"),
+    ("cs", "csharp"),
+    ("go", "go"),
+    ("java", "java"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("scala", "scala"),
+    ("swift", "swift"),
+    ("m", "objectivec"),
+    ("py", "python"),
+    ("pyi", "python"),
+    ("rb", "ruby"),
+    ("php", "php"),
+    ("pl", "perl"),
+    ("lua", "lua"),
+    ("r", "r"),
+    ("dart", "dart"),
+    ("ex", "elixir"),
+    ("exs", "elixir"),
+    ("erl", "erlang"),
+    ("hs", "haskell"),
+    ("ml", "ocaml"),
+    ("clj", "clojure"),
+    ("zig", "zig"),
+    ("nim", "nim"),
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("jsx", "jsx"),
+    ("ts", "typescript"),
+    ("mts", "typescript"),
+    ("tsx", "tsx"),
+    ("vue", "vue"),
+    ("svelte", "svelte"),
+    ("html", "html"),
+    ("htm", "html"),
+    ("css", "css"),
+    ("scss", "scss"),
+    ("sass", "sass"),
+    ("less", "less"),
+    ("json", "json"),
+    ("jsonc", "jsonc"),
+    ("yaml", "yaml"),
+    ("yml", "yaml"),
+    ("xml", "xml"),
+    ("svg", "xml"),
+    ("ini", "ini"),
+    ("cfg", "ini"),
+    ("md", "markdown"),
+    ("markdown", "markdown"),
+    ("rst", "rst"),
+    ("tex", "latex"),
+    ("sql", "sql"),
+    ("graphql", "graphql"),
+    ("proto", "protobuf"),
+    ("sh", "bash"),
+    ("bash", "bash"),
+    ("zsh", "zsh"),
+    ("fish", "fish"),
+    ("ps1", "powershell"),
+    ("bat", "batch"),
+    ("cmd", "batch"),
+    ("tf", "hcl"),
+    ("hcl", "hcl"),
+    ("nix", "nix"),
+    ("cmake", "cmake"),
+    ("mk", "makefile"),
+    ("diff", "diff"),
+    ("patch", "diff"),
+    ("csv", "csv"),
+];
+
+/// Returns the code fence language tag for a file, if it is recognized.
+///
+/// Exact file names such as `Dockerfile` take priority over extensions.
+pub fn language_for(path: &str) -> Option<&'static str> {
+    let path = Path::new(path);
+    let name = path.file_name()?.to_str()?;
+
+    if let Some((_, language)) = FILE_NAMES.iter().find(|(file, _)| *file == name) {
+        return Some(language);
+    }
+
+    if name.starts_with("Dockerfile.") {
+        return Some("Dockerfile");
+    }
+
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, language)| *language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_detection() {
+        assert_eq!(language_for("./src/main.rs"), Some("rust"));
+        assert_eq!(language_for("Cargo.toml"), Some("toml"));
+        assert_eq!(language_for("App.TSX"), Some("tsx"));
+        assert_eq!(language_for("docker/Dockerfile"), Some("Dockerfile"));
+        assert_eq!(language_for("Dockerfile.dev"), Some("Dockerfile"));
+        assert_eq!(language_for("LICENSE"), None);
+        assert_eq!(language_for("data.unknown"), None);
+    }
+}
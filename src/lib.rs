@@ -0,0 +1,481 @@
+//! A safe directory analysis tool for preparing project overviews.
+//!
+//! The library runs the same traversal, detection and rendering as the
+//! `vitax` command and writes the output to any `io::Write`:
+//!
+//! ```no_run
+//! use vitax::{OutputFormat, Vitax};
+//!
+//! let report = Vitax::builder()
+//!     .path("src")
+//!     .extension("rs")
+//!     .ignore("target")
+//!     .format(OutputFormat::Markdown)
+//!     .run(std::io::stdout().lock())?;
+//!
+//! eprintln!("{} files, {} bytes", report.summary.files, report.summary.bytes);
+//! # Ok::<(), vitax::Error>(())
+//! ```
+
+mod budget;
+mod config;
+mod destination;
+mod detector;
+mod excerpt;
+mod filter;
+mod gitignore;
+mod io;
+mod language;
+mod output;
+mod pool;
+mod preset;
+mod probe;
+mod session;
+mod settings;
+mod stats;
+#[cfg(test)]
+mod testing;
+mod tokens;
+mod tree;
+mod units;
+mod validator;
+
+pub use budget::PriorityRules;
+pub use config::{Config, ConfigError};
+pub use destination::{OutputPart, PartLimit};
+pub use excerpt::LineExcerpt;
+pub use filter::FileFilter;
+pub use io::{FollowSymlinks, WalkError};
+pub use output::{OutputFormat, Summary};
+pub use preset::Preset;
+pub use settings::{ConfigFile, Settings};
+pub use stats::{LanguageStats, Stats};
+pub use tokens::Tokenizer;
+pub use tree::TreeMode;
+pub use units::parse_size;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use destination::{AtomicFile, SplitWriter};
+use session::Session;
+
+/// A configured run over one or more input paths.
+#[derive(Debug)]
+pub struct Vitax {
+    config: Config,
+}
+
+impl Vitax {
+    /// Returns a builder for a run with the default settings.
+    pub fn builder() -> VitaxBuilder {
+        VitaxBuilder::default()
+    }
+
+    /// Creates a run from a complete configuration.
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Returns the configuration of the run.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Processes every input path and writes the output to `writer`.
+    ///
+    /// # Arguments
+    /// * `writer` - Destination for the rendered output
+    pub fn run<W: std::io::Write>(&self, writer: W) -> Result<Report, Error> {
+        Session::new(&self.config, writer).run(&self.config)
+    }
+
+    /// Processes every input path and writes the output to the configured
+    /// destination: stdout, the output file, or numbered part files.
+    ///
+    /// Files are only moved into place once the run succeeds, and are listed
+    /// in the report.
+    pub fn write_output(&self) -> Result<Report, Error> {
+        let Some(path) = &self.config.output else {
+            return self.run(std::io::stdout().lock());
+        };
+
+        let Some(limit) = self.config.split else {
+            let mut file = AtomicFile::create(path)?;
+            let mut report = self.run(&mut file)?;
+            report.parts.push(OutputPart {
+                path: path.clone(),
+                bytes: file.bytes(),
+                tokens: None,
+                oversized: false,
+            });
+            file.commit()?;
+            return Ok(report);
+        };
+
+        let mut writer = SplitWriter::new(path, limit);
+        let mut report = self.run(&mut writer)?;
+        report.parts = writer.finish()?;
+        Ok(report)
+    }
+}
+
+/// Builds a `Vitax` run.
+///
+/// Options are collected into a `Settings` layer, so they are validated and
+/// combined with presets exactly like command line options. No configuration
+/// files are read; pass them in with `settings`.
+#[derive(Debug, Default)]
+pub struct VitaxBuilder {
+    paths: Vec<String>,
+    settings: Settings,
+    filter: Option<FileFilter>,
+}
+
+impl VitaxBuilder {
+    /// Adds an input path.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Adds several input paths.
+    pub fn paths<I: IntoIterator<Item = S>, S: Into<String>>(mut self, paths: I) -> Self {
+        self.paths.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Merges a settings layer, such as one read from a configuration file,
+    /// on top of the options set so far.
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = self.settings.merge(settings);
+        self
+    }
+
+    /// Uses a prebuilt filter instead of the extension, include and ignore options.
+    pub fn filter(mut self, filter: FileFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Adds a file extension to include.
+    pub fn extension(mut self, ext: impl Into<String>) -> Self {
+        self.settings.extensions.push(ext.into());
+        self
+    }
+
+    /// Adds a glob pattern for relative paths to include.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.settings.include.push(pattern.into());
+        self
+    }
+
+    /// Adds a pattern to ignore, in gitignore syntax.
+    pub fn ignore(mut self, pattern: impl Into<String>) -> Self {
+        self.settings.ignore.push(pattern.into());
+        self
+    }
+
+    /// Adds a language or ecosystem preset.
+    pub fn preset(mut self, preset: Preset) -> Self {
+        self.settings.preset.push(preset);
+        self
+    }
+
+    /// Sets whether hidden files and directories are shown.
+    pub fn hidden(mut self, show: bool) -> Self {
+        self.settings.all = Some(show);
+        self
+    }
+
+    /// Sets whether .gitignore, .ignore and git exclude files are honored.
+    pub fn ignore_files(mut self, honor: bool) -> Self {
+        self.settings.no_ignore = Some(!honor);
+        self
+    }
+
+    /// Sets the maximum recursion depth.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.settings.depth = Some(depth);
+        self
+    }
+
+    /// Sets the output format.
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.settings.format = Some(format);
+        self
+    }
+
+    /// Sets the directory tree display mode.
+    pub fn tree(mut self, mode: TreeMode) -> Self {
+        self.settings.tree = Some(mode != TreeMode::Off);
+        self.settings.tree_only = Some(mode == TreeMode::Only);
+        self
+    }
+
+    /// Counts tokens per file with the given tokenizer.
+    pub fn tokens(mut self, tokenizer: Tokenizer) -> Self {
+        self.settings.tokens = Some(tokenizer);
+        self.settings.no_tokens = None;
+        self
+    }
+
+    /// Sets the token budget for the whole output.
+    pub fn max_tokens(mut self, budget: usize) -> Self {
+        self.settings.max_tokens = Some(budget);
+        self
+    }
+
+    /// Sets the maximum size of a single file in bytes.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.settings.max_size = Some(bytes);
+        self
+    }
+
+    /// Sets the maximum total size of file contents in bytes.
+    pub fn max_total_size(mut self, bytes: u64) -> Self {
+        self.settings.max_total_size = Some(bytes);
+        self
+    }
+
+    /// Sets which symlinks to follow.
+    pub fn follow_symlinks(mut self, follow: FollowSymlinks) -> Self {
+        self.settings.follow_symlinks = Some(follow);
+        self.settings.no_follow_symlinks = None;
+        self
+    }
+
+    /// Sets the file `write_output` writes to instead of stdout.
+    pub fn output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.settings.output = Some(path.into());
+        self
+    }
+
+    /// Splits the output file into numbered parts of at most this many bytes.
+    pub fn split_size(mut self, bytes: u64) -> Self {
+        self.settings.split_size = Some(bytes);
+        self
+    }
+
+    /// Splits the output file into numbered parts of about this many tokens.
+    pub fn split_tokens(mut self, tokens: usize) -> Self {
+        self.settings.split_tokens = Some(tokens);
+        self
+    }
+
+    /// Sets the number of worker threads.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.settings.jobs = Some(jobs);
+        self
+    }
+
+    /// Sets whether statistics by outcome and language are collected into the summary.
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.settings.stats = Some(stats);
+        self
+    }
+
+    /// Sets whether the run stops at the first unreadable directory or entry.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.settings.strict = Some(strict);
+        self
+    }
+
+    /// Validates the options and creates the run.
+    pub fn build(self) -> Result<Vitax, ConfigError> {
+        let mut config = Config::from_settings(self.paths, self.settings)?;
+        if let Some(filter) = self.filter {
+            config.filter = filter;
+        }
+
+        Ok(Vitax::new(config))
+    }
+
+    /// Validates the options, then processes every input path and writes
+    /// the output to `writer`.
+    ///
+    /// # Arguments
+    /// * `writer` - Destination for the rendered output
+    pub fn run<W: std::io::Write>(self, writer: W) -> Result<Report, Error> {
+        self.build()?.run(writer)
+    }
+}
+
+/// Results of a finished run.
+#[derive(Debug)]
+pub struct Report {
+    /// Totals, token counts and traversal errors, as shown in the summary
+    pub summary: Summary,
+    /// Directories scanned
+    pub directories: usize,
+    /// Directories skipped by ignore rules and filters
+    pub pruned: usize,
+    /// Lower bound on the time saved by skipping those directories: each
+    /// counts as one directory at the average read time, without the
+    /// subdirectories it would have led to
+    pub pruning_saved: Duration,
+    /// Symlinks that were not followed, with the reason
+    pub refused_links: Vec<(String, String)>,
+    /// Input paths that could not be processed, with the reason; they are
+    /// missing from the output
+    pub input_errors: Vec<(String, String)>,
+    /// Time the run took
+    pub elapsed: Duration,
+    /// Files written by `write_output`, in order
+    pub parts: Vec<OutputPart>,
+}
+
+/// Reasons a run fails or stops before finishing the output.
+#[derive(Debug)]
+pub enum Error {
+    /// The options are invalid
+    Config(ConfigError),
+    /// Writing the output failed
+    Output(std::io::Error),
+    /// A directory or entry could not be read in strict mode
+    Traversal(WalkError),
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Output(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Config(e) => write!(f, "{}", e),
+            Error::Output(e) => write!(f, "error writing output: {}", e),
+            Error::Traversal(e) => write!(f, "cannot read {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_builder_run() {
+        let dir = TempDir::new("lib");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("notes.txt"), "notes\n").unwrap();
+        fs::write(dir.join("target/out.rs"), "// generated\n").unwrap();
+
+        let mut output = Vec::new();
+        let report = Vitax::builder()
+            .path(dir.to_string_lossy())
+            .extension("rs")
+            .ignore("target")
+            .format(OutputFormat::JsonLines)
+            .jobs(2)
+            .run(&mut output)
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("fn main() {}"));
+        assert!(!output.contains("notes"));
+        assert!(!output.contains("generated"));
+        assert_eq!(report.summary.files, 1);
+        assert_eq!(report.summary.text, 1);
+        assert_eq!(report.directories, 2);
+        assert_eq!(report.pruned, 1);
+
+        let mut output = Vec::new();
+        let report = Vitax::builder()
+            .path(dir.join("src/main.rs").to_string_lossy())
+            .path(dir.join("notes.txt").to_string_lossy())
+            .include("src/**/*.rs")
+            .extension("rs")
+            .run(&mut output)
+            .unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("fn main() {}"));
+        assert_eq!(report.summary.files, 1);
+
+        let error = Vitax::builder().path(dir.to_string_lossy()).jobs(0).build().unwrap_err();
+        assert!(matches!(error, ConfigError::InvalidJobCount));
+    }
+
+    #[test]
+    fn test_output_inside_input() {
+        let dir = TempDir::new("output-inside");
+        for index in 0..6 {
+            fs::write(dir.join(format!("file_{}.txt", index)), format!("{}\n", "line\n".repeat(20))).unwrap();
+        }
+
+        let run = |builder: VitaxBuilder| {
+            builder.path(dir.to_string_lossy()).hidden(true).build().unwrap().write_output().unwrap()
+        };
+        let listing = || {
+            let mut names: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+            names.sort();
+            names
+        };
+
+        let output = dir.join("dump.txt");
+        let first = run(Vitax::builder().output(&output));
+        let contents = fs::read_to_string(&output).unwrap();
+        let second = run(Vitax::builder().output(&output));
+        assert_eq!(first.summary.files, 6);
+        assert_eq!(second.summary.files, 6);
+        assert_eq!(fs::read_to_string(&output).unwrap(), contents);
+        fs::remove_file(&output).unwrap();
+
+        let split = || Vitax::builder().output(dir.join("out.md")).format(OutputFormat::Markdown).split_size(400);
+        let first = run(split());
+        let files = listing();
+        let second = run(split());
+        assert!(first.parts.len() > 1);
+        assert_eq!(second.parts.len(), first.parts.len());
+        assert_eq!(second.summary.files, 6);
+        assert_eq!(listing(), files);
+    }
+
+    #[test]
+    fn test_budget_covers_rendered_output() {
+        let dir = TempDir::new("budget");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("README.md"), "# Project\n\nAn example project.\n").unwrap();
+        for index in 0..8 {
+            let body: String = (0..40).map(|line| format!("    let value_{} = {} * {};\n", line, line, index)).collect();
+            fs::write(dir.join(format!("src/module_{}.rs", index)), format!("fn run() {{\n{}}}\n", body)).unwrap();
+        }
+
+        let budget = 1500;
+        for format in [
+            OutputFormat::Plain,
+            OutputFormat::Markdown,
+            OutputFormat::Xml,
+            OutputFormat::Json,
+            OutputFormat::JsonLines,
+        ] {
+            let mut output = Vec::new();
+            let report = Vitax::builder()
+                .path(dir.to_string_lossy())
+                .format(format)
+                .tree(TreeMode::WithContents)
+                .max_tokens(budget)
+                .run(&mut output)
+                .unwrap();
+
+            let output = String::from_utf8(output).unwrap();
+            let tokens = Tokenizer::Cl100k.count(&output);
+            assert!(tokens <= budget, "{:?} output has {} tokens", format, tokens);
+            assert!(tokens > budget / 2, "{:?} output has {} tokens", format, tokens);
+            assert!(!report.summary.dropped.is_empty());
+        }
+    }
+}
@@ -1,22 +1,15 @@
 mod cli;
-mod config;
-mod detector;
-mod filter;
-mod io;
-mod validator;
 
-use std::path::Path;
 use std::process;
 
 use clap::Parser;
-use config::Config;
-use detector::{FileDetector, FileType};
-use validator::{FileValidator, ValidationError};
+use cli::Args;
+use vitax::{Config, ConfigError, ConfigFile, Error, OutputFormat, OutputPart, Report, Vitax};
 
 fn main() {
-    let args = cli::Args::parse();
+    let args = Args::parse();
 
-    let config = match Config::from_args(args) {
+    let config = match load_config(args) {
         Ok(cfg) => cfg,
         Err(e) => {
             eprintln!("vitax: fatal error: {}", e);
@@ -26,155 +19,110 @@ fn main() {
         }
     };
 
-    for (index, path) in config.paths.iter().enumerate() {
-        if index > 0 {
-            println!("\n{}", "=".repeat(80));
-            println!();
+    if config.verbose {
+        for file in &config.config_files {
+            eprintln!("vitax: using config {}", file.display());
         }
-        process_single_path(path, &config);
-    }
-}
 
-/// Processes a single path (file or directory).
-///
-/// # Arguments
-/// * `path` - The path to process
-/// * `config` - Application configuration
-fn process_single_path(path: &str, config: &Config) {
-    let base_path = match std::fs::canonicalize(path) {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("Error resolving path '{}': {}", path, e);
-            return;
+        if !config.presets.is_empty() {
+            let names: Vec<String> = config.presets.iter().map(|p| p.to_string()).collect();
+            eprintln!("vitax: presets: {}", names.join(", "));
         }
-    };
+    }
 
-    match io::check_path_type(path) {
-        Ok(io::PathType::Directory) => {
-            process_directory(path, &base_path, config);
+    let vitax = Vitax::new(config);
+    match vitax.write_output() {
+        Ok(report) => {
+            report_walk(&report, vitax.config());
+            report_stats(&report, vitax.config());
+            report_parts(&report.parts, vitax.config());
         }
-        Ok(io::PathType::File) => {
-            if config.filter.should_process(path) {
-                process_file(path, &base_path, true, config);
-            }
-        }
-        Ok(io::PathType::Other) => {
-            eprintln!("Unsupported path type: {}", path);
+        Err(Error::Output(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        Err(e @ Error::Output(_)) => {
+            eprintln!("vitax: {}", e);
+            process::exit(1);
         }
         Err(e) => {
-            eprintln!("Error accessing path '{}': {}", path, e);
+            eprintln!("vitax: fatal error: {}", e);
+            process::exit(1);
         }
     }
 }
 
-/// Processes a directory recursively.
-///
-/// # Arguments
-/// * `path` - Directory path to process
-/// * `base_path` - Base path for relative path calculation
-/// * `config` - Application configuration
-fn process_directory(path: &str, base_path: &Path, config: &Config) {
-    println!("{}", "=".repeat(80));
-    println!("{}/", base_path.display());
-    println!("{}", "=".repeat(80));
-
-    match io::walk_directory(path, Some(config.max_depth)) {
-        Ok(files) => {
-            for file in files {
-                if config.filter.should_process(&file) {
-                    process_file(&file, base_path, false, config);
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Error walking directory '{}': {}", path, e);
-        }
-    }
+/// Builds the configuration from the configuration files and the command line.
+fn load_config(args: Args) -> Result<Config, ConfigError> {
+    let (config_file, config_files) = ConfigFile::load(args.config.as_deref())?;
+    let settings = config_file
+        .resolve(args.profile.as_deref())?
+        .merge(args.settings());
+
+    let mut config = Config::from_settings(args.paths, settings)?;
+    config.config_files = config_files;
+    Ok(config)
 }
 
-/// Processes a single file.
-///
-/// # Arguments
-/// * `path` - File path to process
-/// * `base_path` - Base path for relative path calculation
-/// * `is_root` - Whether this is a root file (affects display formatting)
-/// * `config` - Application configuration
-fn process_file(path: &str, base_path: &Path, is_root: bool, config: &Config) {
-    // Validate file first
-    if let Err(e) = FileValidator::quick_validate(path) {
-        if config.verbose {
-            print_skipped_file(path, base_path, is_root, &e);
-        }
+/// Prints the inputs that could not be processed and, in verbose mode, the
+/// directories scanned, symlinks not followed and unreadable paths.
+fn report_walk(report: &Report, config: &Config) {
+    for (path, error) in &report.input_errors {
+        eprintln!("vitax: {}: {}", path, error);
+    }
+
+    if !config.verbose {
         return;
     }
 
-    let display_path = format_display_path(path, base_path, is_root);
+    if report.directories > 0 {
+        let plural = |count: usize| if count == 1 { "directory" } else { "directories" };
+        let saved = if report.pruned > 0 {
+            format!(" (at least {} ms saved, not counting their subdirectories)", report.pruning_saved.as_millis())
+        } else {
+            String::new()
+        };
+        eprintln!(
+            "vitax: scanned {} {}, skipped {} ignored {}{}",
+            report.directories,
+            plural(report.directories),
+            report.pruned,
+            plural(report.pruned),
+            saved,
+        );
+    }
 
-    match FileDetector::detect_file_type(path) {
-        Ok(FileType::Binary) => {
-            println!("{}", display_path);
-            println!("This is a binary file\n");
-        }
-        Ok(FileType::Text) => {
-            match io::read_file_content(path) {
-                Ok(contents) => {
-                    println!("{}", display_path);
-                    println!("{}\n", contents);
-                }
-                Err(e) => {
-                    if config.verbose {
-                        print_read_error(path, base_path, is_root, &e);
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            if config.verbose {
-                print_detection_error(path, base_path, is_root, &e);
-            }
-        }
+    for (link, reason) in &report.refused_links {
+        eprintln!("vitax: not following symlink {}: {}", link, reason);
     }
-}
 
-/// Prints information about a skipped file in verbose mode.
-fn print_skipped_file(path: &str, base_path: &Path, is_root: bool, error: &ValidationError) {
-    let display_path = format_display_path(path, base_path, is_root);
-    println!("{}", display_path);
-    println!("SKIPPED: {}\n", error);
+    for (path, error) in &report.summary.traversal_errors {
+        eprintln!("vitax: cannot read {}: {}", path, error);
+    }
 }
 
-/// Prints information about a file read error in verbose mode.
-fn print_read_error(path: &str, base_path: &Path, is_root: bool, error: &std::io::Error) {
-    let display_path = format_display_path(path, base_path, is_root);
-    println!("{}", display_path);
-    println!("READ ERROR: {}\n", error);
-}
+/// Prints run statistics when requested; the JSON formats include them in their summary instead.
+fn report_stats(report: &Report, config: &Config) {
+    if matches!(config.format, OutputFormat::Json | OutputFormat::JsonLines) {
+        return;
+    }
 
-/// Prints information about a file type detection error in verbose mode.
-fn print_detection_error(path: &str, base_path: &Path, is_root: bool, error: &std::io::Error) {
-    let display_path = format_display_path(path, base_path, is_root);
-    println!("{}", display_path);
-    println!("DETECTION ERROR: {}\n", error);
+    if let Some(stats) = &report.summary.stats {
+        eprint!("vitax: run statistics\n{}", stats);
+    }
 }
 
-/// Formats the display path for a file.
-fn format_display_path(path: &str, base_path: &Path, is_root: bool) -> String {
-    let separator = if is_root { "=" } else { "-" };
-    let line = separator.repeat(80);
-
-    if is_root {
-        format!("{}\n{}\n{}", line, path, line)
-    } else {
-        let file_path = match std::fs::canonicalize(path) {
-            Ok(p) => p,
-            Err(_) => return format!("{}\n{}\n{}", line, path, line),
-        };
-
-        let relative_path = match file_path.strip_prefix(base_path) {
-            Ok(rel) => format!("./{}", rel.display()),
-            Err(_) => path.to_string(),
-        };
+/// Lists the files written: always for split output, and in verbose mode for a single output file.
+fn report_parts(parts: &[OutputPart], config: &Config) {
+    if config.split.is_none() {
+        if config.verbose {
+            for part in parts {
+                eprintln!("vitax: wrote {}", part);
+            }
+        }
+        return;
+    }
 
-        format!("{}\n{}\n{}", line, relative_path, line)
+    let noun = if parts.len() == 1 { "part" } else { "parts" };
+    eprintln!("vitax: wrote {} {}:", parts.len(), noun);
+    for part in parts {
+        eprintln!("  {}", part);
     }
-}
\ No newline at end of file
+}
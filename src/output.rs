@@ -0,0 +1,1018 @@
+//! Output rendering.
+//!
+//! This module turns processed files into the selected output format.
+//! Every format implements `Renderer`, which receives each input path,
+//! each processed file and the final summary in order.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::detector::Encoding;
+use crate::language;
+use crate::stats::Stats;
+use crate::tokens::Tokenizer;
+use crate::tree::{self, TreeEntry, TreeMode};
+use crate::units::format_count;
+use crate::validator::ValidationError;
+
+/// Number of largest files listed in token summaries.
+const LARGEST_FILES: usize = 10;
+
+/// Supported output formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text with banner lines
+    Plain,
+    /// A single JSON document
+    Json,
+    /// One JSON object per line
+    #[value(name = "jsonl", alias = "json-lines")]
+    JsonLines,
+    /// Markdown headings with fenced code blocks
+    #[value(alias = "md")]
+    Markdown,
+    /// `<document>` elements for structured LLM prompts
+    Xml,
+}
+
+/// Result of processing a single file.
+#[derive(Debug)]
+pub enum FileOutcome {
+    /// Decoded text contents
+    Text { encoding: Encoding, contents: String },
+    /// Binary file, contents omitted
+    Binary,
+    /// Rejected by validation
+    Skipped(ValidationError),
+    /// File type detection failed
+    DetectionError(io::Error),
+    /// Reading or decoding the contents failed
+    ReadError(io::Error),
+    /// Symlink shown without being followed, with the reason
+    Symlink(String),
+}
+
+/// A processed file ready for rendering.
+#[derive(Debug)]
+pub struct FileRecord {
+    /// Display path (relative to the input directory, or as given for root files)
+    pub path: String,
+    /// Whether the file was given directly on the command line
+    pub is_root: bool,
+    /// File size in bytes, if known
+    pub size: Option<u64>,
+    /// Token count of the contents, if counting is enabled
+    pub tokens: Option<usize>,
+    /// Number of lines left out of the contents, if they were shortened
+    pub omitted_lines: Option<usize>,
+    /// Target of the file if it is a symlink
+    pub symlink: Option<String>,
+    /// Processing result
+    pub outcome: FileOutcome,
+}
+
+impl FileRecord {
+    /// Returns the file type name, if the type was determined.
+    pub fn file_type(&self) -> Option<&'static str> {
+        match self.outcome {
+            FileOutcome::Text { .. } | FileOutcome::ReadError(_) => Some("text"),
+            FileOutcome::Binary => Some("binary"),
+            FileOutcome::Skipped(_) | FileOutcome::DetectionError(_) | FileOutcome::Symlink(_) => None,
+        }
+    }
+
+    /// Returns a short machine-readable name for the outcome: `text`,
+    /// `binary`, the validation error kind, `detection_error`, `decode_error`,
+    /// `read_error` or `symlink`.
+    pub fn outcome_kind(&self) -> &'static str {
+        match &self.outcome {
+            FileOutcome::Text { .. } => "text",
+            FileOutcome::Binary => "binary",
+            FileOutcome::Skipped(e) => e.kind(),
+            FileOutcome::DetectionError(_) => "detection_error",
+            FileOutcome::ReadError(e) if e.kind() == io::ErrorKind::InvalidData => "decode_error",
+            FileOutcome::ReadError(_) => "read_error",
+            FileOutcome::Symlink(_) => "symlink",
+        }
+    }
+
+    /// Returns notes shown next to the path: a symlink target, a non-UTF-8
+    /// encoding and the token count.
+    pub fn annotations(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+
+        if let Some(target) = &self.symlink {
+            notes.push(format!("symlink to {}", target));
+        }
+
+        if let FileOutcome::Text { encoding, .. } = &self.outcome {
+            if *encoding != Encoding::Utf8 {
+                notes.push(encoding.to_string());
+            }
+        }
+
+        if let Some(tokens) = self.tokens {
+            notes.push(format!("{} tokens", format_count(tokens)));
+        }
+
+        notes
+    }
+}
+
+/// Totals collected over a run.
+#[derive(Debug, Default, Clone)]
+pub struct Summary {
+    /// Files encountered
+    pub files: usize,
+    /// Text files printed
+    pub text: usize,
+    /// Binary files
+    pub binary: usize,
+    /// Files rejected by validation
+    pub skipped: usize,
+    /// Files that failed detection or reading
+    pub errors: usize,
+    /// Symlinks shown without being followed
+    pub symlinks: usize,
+    /// Total size of printed text files in bytes
+    pub bytes: u64,
+    /// Tokenizer used for counting, if enabled
+    pub tokenizer: Option<Tokenizer>,
+    /// Total tokens across text files
+    pub tokens: usize,
+    /// Token count of each text file, by display path
+    pub file_tokens: Vec<(String, usize)>,
+    /// Token budget, if one was set
+    pub max_tokens: Option<usize>,
+    /// Files truncated to fit the token budget, with their full token counts
+    pub truncated: Vec<(String, usize)>,
+    /// Files left out to fit the token budget, with their token counts
+    pub dropped: Vec<(String, usize)>,
+    /// Directories and entries that could not be read, with the error message
+    pub traversal_errors: Vec<(String, String)>,
+    /// Statistics by outcome and language, if enabled
+    pub stats: Option<Stats>,
+}
+
+impl Summary {
+    /// Accounts for a processed file.
+    pub fn record(&mut self, record: &FileRecord) {
+        self.files += 1;
+        if let Some(tokens) = record.tokens {
+            self.tokens += tokens;
+            self.file_tokens.push((record.path.clone(), tokens));
+        }
+
+        match record.outcome {
+            FileOutcome::Text { .. } => {
+                self.text += 1;
+                self.bytes += record.size.unwrap_or(0);
+            }
+            FileOutcome::Binary => self.binary += 1,
+            FileOutcome::Skipped(_) => self.skipped += 1,
+            FileOutcome::DetectionError(_) | FileOutcome::ReadError(_) => self.errors += 1,
+            FileOutcome::Symlink(_) => self.symlinks += 1,
+        }
+
+        if let Some(stats) = &mut self.stats {
+            stats.record(record);
+        }
+    }
+
+    /// Returns the files with the most tokens, largest first.
+    pub fn largest(&self, limit: usize) -> Vec<&(String, usize)> {
+        let mut files: Vec<_> = self.file_tokens.iter().collect();
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        files.truncate(limit);
+        files
+    }
+
+    /// Describes the token total, including the budget if one was set.
+    fn describe_tokens(&self, tokenizer: Tokenizer) -> String {
+        match self.max_tokens {
+            Some(max) => format!(
+                "{} of {} tokens ({})",
+                format_count(self.tokens),
+                format_count(max),
+                tokenizer,
+            ),
+            None => format!("{} tokens ({})", format_count(self.tokens), tokenizer),
+        }
+    }
+}
+
+/// Receives processing events and writes them in a specific format.
+pub trait Renderer {
+    /// Called before each input path is processed.
+    fn begin_input(&mut self, index: usize, base_path: &Path, is_dir: bool) -> io::Result<()>;
+
+    /// Called with the files about to be processed from a directory input.
+    ///
+    /// # Arguments
+    /// * `base_path` - The directory being processed
+    /// * `entries` - Files relative to `base_path`, in output order
+    fn tree(&mut self, _base_path: &Path, _entries: &[TreeEntry]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Whether `tree` produces output, so that files must be collected before
+    /// any can be rendered.
+    fn wants_tree(&self) -> bool {
+        false
+    }
+
+    /// Called for every processed file.
+    ///
+    /// Formats that can be split into part files flush the writer afterwards,
+    /// marking a point where the output may be divided.
+    fn file(&mut self, record: &FileRecord) -> io::Result<()>;
+
+    /// Called once after all input paths are processed.
+    fn finish(&mut self, summary: &Summary) -> io::Result<()>;
+}
+
+/// Presentation options shared by the renderers.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    /// Whether skipped files and errors are shown in text-based formats
+    pub verbose: bool,
+    /// Whether to show a directory tree diagram
+    pub tree: TreeMode,
+}
+
+/// Creates a renderer for the given format.
+///
+/// # Arguments
+/// * `format` - The output format
+/// * `writer` - Destination for the rendered output
+/// * `options` - Presentation options
+pub fn create_renderer<'a, W: Write + 'a>(
+    format: OutputFormat,
+    writer: W,
+    options: RenderOptions,
+) -> Box<dyn Renderer + 'a> {
+    match format {
+        OutputFormat::Plain => Box::new(PlainRenderer { writer, options }),
+        OutputFormat::Json => Box::new(JsonRenderer {
+            writer,
+            options,
+            root: String::new(),
+            count: 0,
+            trees: Vec::new(),
+        }),
+        OutputFormat::JsonLines => Box::new(JsonLinesRenderer { writer, options, root: String::new() }),
+        OutputFormat::Markdown => Box::new(MarkdownRenderer { writer, options }),
+        OutputFormat::Xml => Box::new(XmlRenderer { writer, options, started: false }),
+    }
+}
+
+/// The original human-readable format with `=` and `-` banner lines.
+struct PlainRenderer<W: Write> {
+    writer: W,
+    options: RenderOptions,
+}
+
+impl<W: Write> PlainRenderer<W> {
+    fn header(&mut self, record: &FileRecord) -> io::Result<()> {
+        let separator = if record.is_root { "=" } else { "-" };
+        let line = separator.repeat(80);
+        let notes = record.annotations();
+
+        if notes.is_empty() {
+            writeln!(self.writer, "{}\n{}\n{}", line, record.path, line)
+        } else {
+            writeln!(self.writer, "{}\n{} ({})\n{}", line, record.path, notes.join(", "), line)
+        }
+    }
+}
+
+impl<W: Write> Renderer for PlainRenderer<W> {
+    fn begin_input(&mut self, index: usize, base_path: &Path, is_dir: bool) -> io::Result<()> {
+        if self.options.tree == TreeMode::Only {
+            if index > 0 {
+                writeln!(self.writer)?;
+            }
+            return Ok(());
+        }
+
+        if index > 0 {
+            writeln!(self.writer, "\n{}", "=".repeat(80))?;
+            writeln!(self.writer)?;
+        }
+
+        if is_dir {
+            writeln!(self.writer, "{}", "=".repeat(80))?;
+            writeln!(self.writer, "{}/", base_path.display())?;
+            writeln!(self.writer, "{}", "=".repeat(80))?;
+        }
+
+        Ok(())
+    }
+
+    fn wants_tree(&self) -> bool {
+        self.options.tree != TreeMode::Off
+    }
+
+    fn tree(&mut self, base_path: &Path, entries: &[TreeEntry]) -> io::Result<()> {
+        match self.options.tree {
+            TreeMode::Off => Ok(()),
+            TreeMode::WithContents => {
+                writeln!(self.writer, "{}", tree::render_tree(".", entries, false))
+            }
+            TreeMode::Only => {
+                let root = format!("{}/", base_path.display());
+                write!(self.writer, "{}", tree::render_tree(&root, entries, true))
+            }
+        }
+    }
+
+    fn file(&mut self, record: &FileRecord) -> io::Result<()> {
+        match &record.outcome {
+            FileOutcome::Text { contents, .. } => {
+                self.header(record)?;
+                writeln!(self.writer, "{}\n", contents)
+            }
+            FileOutcome::Binary => {
+                self.header(record)?;
+                writeln!(self.writer, "This is a binary file\n")
+            }
+            FileOutcome::Symlink(reason) => {
+                self.header(record)?;
+                writeln!(self.writer, "SYMLINK NOT FOLLOWED: {}\n", reason)
+            }
+            FileOutcome::Skipped(e) if self.options.verbose => {
+                self.header(record)?;
+                writeln!(self.writer, "SKIPPED: {}\n", e)
+            }
+            FileOutcome::ReadError(e) if self.options.verbose => {
+                self.header(record)?;
+                writeln!(self.writer, "READ ERROR: {}\n", e)
+            }
+            FileOutcome::DetectionError(e) if self.options.verbose => {
+                self.header(record)?;
+                writeln!(self.writer, "DETECTION ERROR: {}\n", e)
+            }
+            _ => Ok(()),
+        }?;
+        self.writer.flush()
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        if let Some(tokenizer) = summary.tokenizer {
+            let line = "=".repeat(80);
+            writeln!(self.writer, "{}", line)?;
+            writeln!(self.writer, "Tokens: {}", summary.describe_tokens(tokenizer))?;
+            writeln!(self.writer, "{}", line)?;
+            for (path, tokens) in summary.largest(LARGEST_FILES) {
+                writeln!(self.writer, "{:>10}  {}", format_count(*tokens), path)?;
+            }
+
+            for (title, files) in [("Truncated", &summary.truncated), ("Dropped", &summary.dropped)] {
+                if !files.is_empty() {
+                    writeln!(self.writer, "\n{} to fit the token budget:", title)?;
+                    for (path, tokens) in files {
+                        writeln!(self.writer, "{:>10}  {}", format_count(*tokens), path)?;
+                    }
+                }
+            }
+        }
+
+        if !summary.traversal_errors.is_empty() {
+            let line = "=".repeat(80);
+            writeln!(self.writer, "{}", line)?;
+            writeln!(self.writer, "Could not read {} {}:", summary.traversal_errors.len(), plural_paths(summary))?;
+            writeln!(self.writer, "{}", line)?;
+            for (path, error) in &summary.traversal_errors {
+                writeln!(self.writer, "{}: {}", path, error)?;
+            }
+        }
+
+        self.writer.flush()
+    }
+}
+
+/// A single JSON document: `{"files": [...], "trees": [...], "summary": {...}}`.
+///
+/// `trees` holds one object per directory input when a tree is requested,
+/// and is `null` otherwise.
+struct JsonRenderer<W: Write> {
+    writer: W,
+    options: RenderOptions,
+    root: String,
+    count: usize,
+    trees: Vec<String>,
+}
+
+impl<W: Write> Renderer for JsonRenderer<W> {
+    fn begin_input(&mut self, _index: usize, base_path: &Path, _is_dir: bool) -> io::Result<()> {
+        self.root = base_path.display().to_string();
+        Ok(())
+    }
+
+    fn wants_tree(&self) -> bool {
+        self.options.tree != TreeMode::Off
+    }
+
+    fn tree(&mut self, base_path: &Path, entries: &[TreeEntry]) -> io::Result<()> {
+        if self.options.tree != TreeMode::Off {
+            self.trees.push(tree_json(base_path, entries));
+        }
+        Ok(())
+    }
+
+    fn file(&mut self, record: &FileRecord) -> io::Result<()> {
+        let prefix = if self.count == 0 { "{\"files\":[" } else { "," };
+        self.count += 1;
+        write!(self.writer, "{}{}", prefix, file_json(record, &self.root))
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        let prefix = if self.count == 0 { "{\"files\":[" } else { "" };
+        let trees = match self.options.tree {
+            TreeMode::Off => "null".to_string(),
+            _ => format!("[{}]", self.trees.join(",")),
+        };
+        writeln!(self.writer, "{}],\"trees\":{},\"summary\":{}}}", prefix, trees, summary_json(summary))?;
+        self.writer.flush()
+    }
+}
+
+/// JSON Lines: one `file` object per line, followed by a `summary` object.
+///
+/// When a tree is requested, each directory input starts with a `tree` object.
+struct JsonLinesRenderer<W: Write> {
+    writer: W,
+    options: RenderOptions,
+    root: String,
+}
+
+impl<W: Write> Renderer for JsonLinesRenderer<W> {
+    fn begin_input(&mut self, _index: usize, base_path: &Path, _is_dir: bool) -> io::Result<()> {
+        self.root = base_path.display().to_string();
+        Ok(())
+    }
+
+    fn wants_tree(&self) -> bool {
+        self.options.tree != TreeMode::Off
+    }
+
+    fn tree(&mut self, base_path: &Path, entries: &[TreeEntry]) -> io::Result<()> {
+        if self.options.tree == TreeMode::Off {
+            return Ok(());
+        }
+        writeln!(self.writer, "{}", tree_json(base_path, entries))?;
+        self.writer.flush()
+    }
+
+    fn file(&mut self, record: &FileRecord) -> io::Result<()> {
+        writeln!(self.writer, "{}", file_json(record, &self.root))?;
+        self.writer.flush()
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        writeln!(self.writer, "{}", summary_json(summary))?;
+        self.writer.flush()
+    }
+}
+
+/// Markdown with a heading per file and contents in fenced code blocks.
+struct MarkdownRenderer<W: Write> {
+    writer: W,
+    options: RenderOptions,
+}
+
+impl<W: Write> MarkdownRenderer<W> {
+    fn note(&mut self, record: &FileRecord, note: &str) -> io::Result<()> {
+        writeln!(self.writer, "## {}\n\n_{}_\n", record.path, note)
+    }
+}
+
+impl<W: Write> Renderer for MarkdownRenderer<W> {
+    fn begin_input(&mut self, _index: usize, base_path: &Path, is_dir: bool) -> io::Result<()> {
+        if is_dir && self.options.tree != TreeMode::Only {
+            writeln!(self.writer, "# {}/\n", base_path.display())?;
+        }
+        Ok(())
+    }
+
+    fn wants_tree(&self) -> bool {
+        self.options.tree != TreeMode::Off
+    }
+
+    fn tree(&mut self, base_path: &Path, entries: &[TreeEntry]) -> io::Result<()> {
+        let (root, show_sizes) = match self.options.tree {
+            TreeMode::Off => return Ok(()),
+            TreeMode::WithContents => (".".to_string(), false),
+            TreeMode::Only => (format!("{}/", base_path.display()), true),
+        };
+
+        let diagram = tree::render_tree(&root, entries, show_sizes);
+        writeln!(self.writer, "```text\n{}```\n", diagram)
+    }
+
+    fn file(&mut self, record: &FileRecord) -> io::Result<()> {
+        match &record.outcome {
+            FileOutcome::Text { contents, .. } => {
+                writeln!(self.writer, "## {}\n", record.path)?;
+                let notes = record.annotations();
+                if !notes.is_empty() {
+                    writeln!(self.writer, "_{}_\n", notes.join(" · "))?;
+                }
+
+                let fence = code_fence(contents);
+                let language = language::language_for(&record.path).unwrap_or("");
+                let newline = if contents.ends_with('\n') || contents.is_empty() { "" } else { "\n" };
+                writeln!(self.writer, "{}{}\n{}{}{}\n", fence, language, contents, newline, fence)
+            }
+            FileOutcome::Binary => self.note(record, "Binary file, contents omitted."),
+            FileOutcome::Symlink(reason) => {
+                let target = record.symlink.as_deref().unwrap_or("");
+                self.note(record, &format!("Symlink to `{}`, not followed: {}.", target, reason))
+            }
+            FileOutcome::Skipped(e) if self.options.verbose => self.note(record, &format!("Skipped: {}", e)),
+            FileOutcome::ReadError(e) if self.options.verbose => self.note(record, &format!("Read error: {}", e)),
+            FileOutcome::DetectionError(e) if self.options.verbose => {
+                self.note(record, &format!("Detection error: {}", e))
+            }
+            _ => Ok(()),
+        }?;
+        self.writer.flush()
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        if let Some(tokenizer) = summary.tokenizer {
+            writeln!(self.writer, "## Token summary\n")?;
+            writeln!(self.writer, "Total: {}\n", summary.describe_tokens(tokenizer))?;
+            writeln!(self.writer, "| Tokens | File |\n|---:|---|")?;
+            for (path, tokens) in summary.largest(LARGEST_FILES) {
+                writeln!(self.writer, "| {} | `{}` |", format_count(*tokens), path)?;
+            }
+
+            for (title, files) in [("Truncated", &summary.truncated), ("Dropped", &summary.dropped)] {
+                if !files.is_empty() {
+                    writeln!(self.writer, "\n{} to fit the token budget:\n", title)?;
+                    for (path, tokens) in files {
+                        writeln!(self.writer, "- `{}` ({} tokens)", path, format_count(*tokens))?;
+                    }
+                }
+            }
+        }
+
+        if !summary.traversal_errors.is_empty() {
+            writeln!(self.writer, "## Could not read {} {}\n", summary.traversal_errors.len(), plural_paths(summary))?;
+            for (path, error) in &summary.traversal_errors {
+                writeln!(self.writer, "- `{}`: {}", path, error)?;
+            }
+        }
+
+        self.writer.flush()
+    }
+}
+
+/// Chooses a backtick fence longer than any backtick run in the contents.
+fn code_fence(contents: &str) -> String {
+    let longest_run = contents
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+
+    "`".repeat(std::cmp::max(3, longest_run + 1))
+}
+
+/// Files wrapped in `<document path="...">` elements inside `<documents>`.
+struct XmlRenderer<W: Write> {
+    writer: W,
+    options: RenderOptions,
+    started: bool,
+}
+
+impl<W: Write> XmlRenderer<W> {
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            writeln!(self.writer, "<documents>")?;
+        }
+        Ok(())
+    }
+
+    fn empty_document(&mut self, record: &FileRecord, attribute: &str, value: &str) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "<document path=\"{}\" {}=\"{}\"/>",
+            xml_escape(&record.path),
+            attribute,
+            xml_escape(value),
+        )
+    }
+}
+
+impl<W: Write> Renderer for XmlRenderer<W> {
+    fn begin_input(&mut self, _index: usize, _base_path: &Path, _is_dir: bool) -> io::Result<()> {
+        self.start()
+    }
+
+    fn wants_tree(&self) -> bool {
+        true
+    }
+
+    fn tree(&mut self, base_path: &Path, entries: &[TreeEntry]) -> io::Result<()> {
+        let diagram = tree::render_tree(".", entries, self.options.tree == TreeMode::Only);
+        writeln!(
+            self.writer,
+            "<directory_tree root=\"{}\">\n{}</directory_tree>",
+            xml_escape(&base_path.display().to_string()),
+            xml_escape(&diagram),
+        )
+    }
+
+    fn file(&mut self, record: &FileRecord) -> io::Result<()> {
+        match &record.outcome {
+            FileOutcome::Text { encoding, contents } => {
+                let newline = if contents.ends_with('\n') || contents.is_empty() { "" } else { "\n" };
+                let tokens = record.tokens.map_or(String::new(), |t| format!(" tokens=\"{}\"", t));
+                let symlink = record
+                    .symlink
+                    .as_ref()
+                    .map_or(String::new(), |t| format!(" symlink=\"{}\"", xml_escape(t)));
+                writeln!(
+                    self.writer,
+                    "<document path=\"{}\" encoding=\"{}\"{}{}>\n{}{}</document>",
+                    xml_escape(&record.path),
+                    encoding,
+                    tokens,
+                    symlink,
+                    xml_cdata(contents),
+                    newline,
+                )
+            }
+            FileOutcome::Binary => self.empty_document(record, "type", "binary"),
+            FileOutcome::Symlink(reason) => writeln!(
+                self.writer,
+                "<document path=\"{}\" symlink=\"{}\" skipped=\"{}\"/>",
+                xml_escape(&record.path),
+                xml_escape(record.symlink.as_deref().unwrap_or("")),
+                xml_escape(reason),
+            ),
+            FileOutcome::Skipped(e) if self.options.verbose => {
+                self.empty_document(record, "skipped", &e.to_string())
+            }
+            FileOutcome::ReadError(e) | FileOutcome::DetectionError(e) if self.options.verbose => {
+                self.empty_document(record, "error", &e.to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        self.start()?;
+
+        if let Some(tokenizer) = summary.tokenizer {
+            let budget = summary.max_tokens.map_or(String::new(), |max| format!(" budget=\"{}\"", max));
+            writeln!(
+                self.writer,
+                "<token_summary total=\"{}\"{} tokenizer=\"{}\">",
+                summary.tokens,
+                budget,
+                tokenizer,
+            )?;
+            for (path, tokens) in summary.largest(LARGEST_FILES) {
+                writeln!(self.writer, "<file path=\"{}\" tokens=\"{}\"/>", xml_escape(path), tokens)?;
+            }
+            for (element, files) in [("truncated", &summary.truncated), ("dropped", &summary.dropped)] {
+                for (path, tokens) in files {
+                    writeln!(self.writer, "<{} path=\"{}\" tokens=\"{}\"/>", element, xml_escape(path), tokens)?;
+                }
+            }
+            writeln!(self.writer, "</token_summary>")?;
+        }
+
+        for (path, error) in &summary.traversal_errors {
+            writeln!(
+                self.writer,
+                "<traversal_error path=\"{}\">{}</traversal_error>",
+                xml_escape(path),
+                xml_escape(error),
+            )?;
+        }
+
+        writeln!(self.writer, "</documents>")?;
+        self.writer.flush()
+    }
+}
+
+/// Returns "path" or "paths" to match the number of traversal errors.
+fn plural_paths(summary: &Summary) -> &'static str {
+    if summary.traversal_errors.len() == 1 { "path" } else { "paths" }
+}
+
+/// Escapes text for use in XML attribute values.
+fn xml_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Wraps text in a CDATA section, splitting any `]]>` terminators it contains.
+fn xml_cdata(value: &str) -> String {
+    if value.is_empty() {
+        return String::new();
+    }
+
+    format!("<![CDATA[{}]]>", value.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Serializes a file record as a JSON object.
+fn file_json(record: &FileRecord, root: &str) -> String {
+    let (encoding, skip_reason, error, contents) = match &record.outcome {
+        FileOutcome::Text { encoding, contents } => {
+            (Some(encoding.to_string()), None, None, Some(contents.as_str()))
+        }
+        FileOutcome::Binary => (None, None, None, None),
+        FileOutcome::Skipped(e) => (None, Some(e.to_string()), None, None),
+        FileOutcome::DetectionError(e) | FileOutcome::ReadError(e) => {
+            (None, None, Some(e.to_string()), None)
+        }
+        FileOutcome::Symlink(reason) => (None, Some(reason.clone()), None, None),
+    };
+
+    format!(
+        "{{\"type\":\"file\",\"root\":{},\"path\":{},\"size\":{},\"tokens\":{},\"omitted_lines\":{},\"symlink\":{},\"file_type\":{},\"encoding\":{},\"skip_reason\":{},\"error\":{},\"contents\":{}}}",
+        json_string(root),
+        json_string(&record.path),
+        json_number(record.size),
+        json_number(record.tokens),
+        json_number(record.omitted_lines),
+        json_optional(record.symlink.as_deref()),
+        json_optional(record.file_type()),
+        json_optional(encoding.as_deref()),
+        json_optional(skip_reason.as_deref()),
+        json_optional(error.as_deref()),
+        json_optional(contents),
+    )
+}
+
+/// Serializes a directory tree as a JSON object listing its entries in order.
+fn tree_json(base_path: &Path, entries: &[TreeEntry]) -> String {
+    let items: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{{\"path\":{},\"size\":{},\"symlink\":{}}}",
+                json_string(&entry.path),
+                json_number(entry.size),
+                json_optional(entry.symlink.as_deref()),
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"type\":\"tree\",\"root\":{},\"entries\":[{}]}}",
+        json_string(&base_path.display().to_string()),
+        items.join(","),
+    )
+}
+
+/// Serializes the run summary as a JSON object.
+fn summary_json(summary: &Summary) -> String {
+    let (tokenizer, tokens, largest) = match summary.tokenizer {
+        Some(tokenizer) => (
+            json_string(&tokenizer.to_string()),
+            summary.tokens.to_string(),
+            token_list_json(summary.largest(LARGEST_FILES)),
+        ),
+        None => ("null".to_string(), "null".to_string(), "null".to_string()),
+    };
+
+    let (truncated, dropped) = match summary.max_tokens {
+        Some(_) => (
+            token_list_json(summary.truncated.iter()),
+            token_list_json(summary.dropped.iter()),
+        ),
+        None => ("null".to_string(), "null".to_string()),
+    };
+
+    let traversal_errors: Vec<String> = summary
+        .traversal_errors
+        .iter()
+        .map(|(path, error)| format!("{{\"path\":{},\"error\":{}}}", json_string(path), json_string(error)))
+        .collect();
+    let traversal_errors = format!("[{}]", traversal_errors.join(","));
+
+    let stats = summary.stats.as_ref().map_or("null".to_string(), stats_json);
+
+    format!(
+        "{{\"type\":\"summary\",\"files\":{},\"text\":{},\"binary\":{},\"skipped\":{},\"errors\":{},\"symlinks\":{},\"bytes\":{},\"tokenizer\":{},\"tokens\":{},\"max_tokens\":{},\"largest\":{},\"truncated\":{},\"dropped\":{},\"traversal_errors\":{},\"stats\":{}}}",
+        summary.files,
+        summary.text,
+        summary.binary,
+        summary.skipped,
+        summary.errors,
+        summary.symlinks,
+        summary.bytes,
+        tokenizer,
+        tokens,
+        json_number(summary.max_tokens),
+        largest,
+        truncated,
+        dropped,
+        traversal_errors,
+        stats,
+    )
+}
+
+/// Serializes run statistics as a JSON object.
+fn stats_json(stats: &Stats) -> String {
+    let outcomes: Vec<String> = stats
+        .outcomes
+        .iter()
+        .map(|(kind, count)| format!("{}:{}", json_string(kind), count))
+        .collect();
+
+    let languages: Vec<String> = stats
+        .by_language()
+        .into_iter()
+        .map(|(name, language)| {
+            format!(
+                "{{\"language\":{},\"files\":{},\"bytes\":{},\"lines\":{}}}",
+                json_string(name),
+                language.files,
+                language.bytes,
+                language.lines,
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"files\":{},\"bytes\":{},\"lines\":{},\"directories\":{},\"pruned\":{},\"pruning_saved_min_ms\":{},\"elapsed_ms\":{},\"outcomes\":{{{}}},\"languages\":[{}]}}",
+        stats.files(),
+        stats.bytes,
+        stats.lines,
+        stats.directories,
+        stats.pruned,
+        stats.pruning_saved.as_millis(),
+        stats.elapsed.as_millis(),
+        outcomes.join(","),
+        languages.join(","),
+    )
+}
+
+/// Serializes `(path, tokens)` pairs as a JSON array of objects.
+fn token_list_json<'a>(files: impl IntoIterator<Item = &'a (String, usize)>) -> String {
+    let items: Vec<String> = files
+        .into_iter()
+        .map(|(path, tokens)| format!("{{\"path\":{},\"tokens\":{}}}", json_string(path), tokens))
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn json_optional(value: Option<&str>) -> String {
+    value.map_or("null".to_string(), json_string)
+}
+
+fn json_number<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or("null".to_string(), |v| v.to_string())
+}
+
+/// Encodes a string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string_escaping() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(json_string("line\n\ttab\u{1}"), "\"line\\n\\ttab\\u0001\"");
+    }
+
+    #[test]
+    fn test_code_fence_length() {
+        assert_eq!(code_fence("fn main() {}"), "```");
+        assert_eq!(code_fence("```rust\n```"), "````");
+        assert_eq!(code_fence("inline `code` and `````"), "``````");
+    }
+
+    #[test]
+    fn test_xml_escaping() {
+        assert_eq!(xml_escape("a<b & \"c\""), "a&lt;b &amp; &quot;c&quot;");
+        assert_eq!(xml_cdata("if a < b {}"), "<![CDATA[if a < b {}]]>");
+        assert_eq!(xml_cdata("x]]>y"), "<![CDATA[x]]]]><![CDATA[>y]]>");
+    }
+
+    #[test]
+    fn test_largest_files() {
+        let summary = Summary {
+            file_tokens: vec![
+                ("./b.rs".to_string(), 10),
+                ("./a.rs".to_string(), 10),
+                ("./c.rs".to_string(), 50),
+            ],
+            ..Summary::default()
+        };
+
+        let largest: Vec<&str> = summary.largest(2).iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(largest, vec!["./c.rs", "./a.rs"]);
+    }
+
+    #[test]
+    fn test_json_document() {
+        let mut buffer = Vec::new();
+        {
+            let options = RenderOptions { verbose: false, tree: TreeMode::Off };
+            let mut renderer = create_renderer(OutputFormat::Json, &mut buffer, options);
+            let mut summary = Summary::default();
+            renderer.begin_input(0, Path::new("/project"), true).unwrap();
+
+            let record = FileRecord {
+                path: "./main.rs".to_string(),
+                is_root: false,
+                size: Some(3),
+                tokens: None,
+                omitted_lines: None,
+                symlink: None,
+                outcome: FileOutcome::Text { encoding: Encoding::Utf8, contents: "fn\n".to_string() },
+            };
+            summary.record(&record);
+            renderer.file(&record).unwrap();
+
+            let record = FileRecord {
+                path: "./big.bin".to_string(),
+                is_root: false,
+                size: None,
+                tokens: None,
+                omitted_lines: None,
+                symlink: None,
+                outcome: FileOutcome::Skipped(ValidationError::SuspiciousContent),
+            };
+            summary.record(&record);
+            renderer.file(&record).unwrap();
+            renderer.finish(&summary).unwrap();
+        }
+
+        let output = String::from_utf8(buffer).unwrap();
+        assert!(output.starts_with("{\"files\":[{\"type\":\"file\",\"root\":\"/project\",\"path\":\"./main.rs\",\"size\":3,\"tokens\":null,\"omitted_lines\":null,"));
+        assert!(output.contains("\"encoding\":\"UTF-8\",\"skip_reason\":null,\"error\":null,\"contents\":\"fn\\n\"}"));
+        assert!(output.contains("\"skip_reason\":\"File contains suspicious content\""));
+        assert!(output.ends_with("],\"trees\":null,\"summary\":{\"type\":\"summary\",\"files\":2,\"text\":1,\"binary\":0,\"skipped\":1,\"errors\":0,\"symlinks\":0,\"bytes\":3,\"tokenizer\":null,\"tokens\":null,\"max_tokens\":null,\"largest\":null,\"truncated\":null,\"dropped\":null,\"traversal_errors\":[],\"stats\":null}}\n"));
+    }
+
+    #[test]
+    fn test_json_lines_tree() {
+        let mut buffer = Vec::new();
+        {
+            let options = RenderOptions { verbose: false, tree: TreeMode::Only };
+            let mut renderer = create_renderer(OutputFormat::JsonLines, &mut buffer, options);
+            assert!(renderer.wants_tree());
+
+            let entries = vec![
+                TreeEntry { path: "src/main.rs".to_string(), size: Some(12), symlink: None },
+                TreeEntry { path: "latest.rs".to_string(), size: None, symlink: Some("v2.rs".to_string()) },
+            ];
+            renderer.begin_input(0, Path::new("/project"), true).unwrap();
+            renderer.tree(Path::new("/project"), &entries).unwrap();
+            renderer.finish(&Summary::default()).unwrap();
+        }
+
+        let output = String::from_utf8(buffer).unwrap();
+        let first = output.lines().next().unwrap();
+        assert_eq!(
+            first,
+            "{\"type\":\"tree\",\"root\":\"/project\",\"entries\":[{\"path\":\"src/main.rs\",\"size\":12,\"symlink\":null},{\"path\":\"latest.rs\",\"size\":null,\"symlink\":\"v2.rs\"}]}"
+        );
+        assert_eq!(output.lines().count(), 2);
+    }
+}
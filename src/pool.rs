@@ -0,0 +1,145 @@
+//! Ordered parallel processing.
+//!
+//! Runs a function over a stream of items on a fixed number of worker
+//! threads and hands the results back in input order, so output stays
+//! deterministic however the work is scheduled. Only a bounded window of
+//! items is in flight at a time, keeping memory flat on long streams.
+
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Items in flight per worker before waiting for results.
+const ITEMS_PER_WORKER: usize = 4;
+
+/// Returns the default number of worker threads: one per available CPU.
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Applies `work` to every item on `jobs` threads and passes the results to `sink` in input order.
+///
+/// The items are pulled and `sink` is called on the calling thread. With a
+/// single job everything runs on the calling thread. Stops at the first
+/// error returned by `sink`.
+///
+/// # Arguments
+/// * `items` - Items to process, in output order
+/// * `jobs` - Number of worker threads
+/// * `work` - Function applied to each item
+/// * `sink` - Receives each result in input order
+pub fn map_ordered<T, R, E>(
+    items: impl Iterator<Item = T>,
+    jobs: usize,
+    work: impl Fn(T) -> R + Sync,
+    mut sink: impl FnMut(R) -> Result<(), E>,
+) -> Result<(), E>
+where
+    T: Send,
+    R: Send,
+{
+    if jobs <= 1 {
+        for item in items {
+            sink(work(item))?;
+        }
+        return Ok(());
+    }
+
+    thread::scope(|scope| {
+        let (work_tx, work_rx) = mpsc::channel::<(usize, T)>();
+        let (result_tx, result_rx) = mpsc::channel::<(usize, thread::Result<R>)>();
+        let work_rx = Arc::new(Mutex::new(work_rx));
+
+        for _ in 0..jobs {
+            let result_tx = result_tx.clone();
+            let work_rx = Arc::clone(&work_rx);
+            let work = &work;
+            scope.spawn(move || loop {
+                let next = work_rx.lock().map_err(|_| ()).and_then(|rx| rx.recv().map_err(|_| ()));
+                let Ok((index, item)) = next else {
+                    break;
+                };
+                // Panics are passed back so the calling thread never waits on a lost item
+                let result = panic::catch_unwind(AssertUnwindSafe(|| work(item)));
+                if result_tx.send((index, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_tx);
+
+        let mut items = items.fuse();
+        let mut pending = BTreeMap::new();
+        let (mut sent, mut emitted) = (0, 0);
+
+        loop {
+            while sent - emitted < jobs * ITEMS_PER_WORKER {
+                let Some(item) = items.next() else {
+                    break;
+                };
+                // Workers only stop once this sender is dropped
+                let _ = work_tx.send((sent, item));
+                sent += 1;
+            }
+
+            if emitted == sent {
+                return Ok(());
+            }
+
+            let Ok((index, result)) = result_rx.recv() else {
+                panic!("worker threads stopped before finishing their work");
+            };
+            pending.insert(index, result);
+
+            while let Some(result) = pending.remove(&emitted) {
+                emitted += 1;
+                match result {
+                    Ok(result) => sink(result)?,
+                    Err(payload) => panic::resume_unwind(payload),
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_results_keep_input_order() {
+        let mut results = Vec::new();
+        let outcome: Result<(), ()> = map_ordered(
+            0..50u64,
+            4,
+            |n| {
+                thread::sleep(Duration::from_millis((50 - n) % 7));
+                n * 2
+            },
+            |n| {
+                results.push(n);
+                Ok(())
+            },
+        );
+
+        assert!(outcome.is_ok());
+        assert_eq!(results, (0..50).map(|n| n * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sink_error_stops_processing() {
+        for jobs in [1, 3] {
+            let mut seen = Vec::new();
+            let outcome = map_ordered(0..100, jobs, |n| n, |n| {
+                seen.push(n);
+                if n == 5 { Err(n) } else { Ok(()) }
+            });
+
+            assert_eq!(outcome, Err(5));
+            assert_eq!(seen, vec![0, 1, 2, 3, 4, 5]);
+        }
+    }
+}
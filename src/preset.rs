@@ -0,0 +1,209 @@
+//! Language and ecosystem presets.
+//!
+//! A preset bundles the ignore patterns and extensions that suit a kind of
+//! project: build output, dependency directories and lockfiles are skipped,
+//! and only source, manifest and documentation files are included. Presets
+//! sit below configuration files and the command line, so explicit
+//! extensions replace the preset's and explicit ignores add to them.
+
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::settings::Settings;
+
+/// Supported presets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Cargo projects
+    Rust,
+    /// JavaScript and TypeScript projects
+    Node,
+    /// Python projects
+    Python,
+    /// Go modules
+    Go,
+    /// Maven and Gradle projects
+    Java,
+    /// Detect presets from marker files in the input directories
+    Auto,
+}
+
+/// Every preset that `auto` can detect.
+const DETECTABLE: &[Preset] = &[Preset::Rust, Preset::Node, Preset::Python, Preset::Go, Preset::Java];
+
+impl Preset {
+    /// Files whose presence in a directory marks a project of this kind.
+    fn markers(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Rust => &["Cargo.toml"],
+            Preset::Node => &["package.json"],
+            Preset::Python => &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt", "Pipfile"],
+            Preset::Go => &["go.mod"],
+            Preset::Java => &["pom.xml", "build.gradle", "build.gradle.kts", "settings.gradle"],
+            Preset::Auto => &[],
+        }
+    }
+
+    /// Patterns to ignore.
+    fn ignore(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Rust => &["target", "Cargo.lock"],
+            Preset::Node => &[
+                "node_modules",
+                "dist",
+                "build",
+                "coverage",
+                ".next",
+                ".nuxt",
+                "*.min.js",
+                "package-lock.json",
+                "yarn.lock",
+                "pnpm-lock.yaml",
+            ],
+            Preset::Python => &[
+                "__pycache__",
+                "*.pyc",
+                ".venv",
+                "venv",
+                ".tox",
+                ".pytest_cache",
+                ".mypy_cache",
+                "*.egg-info",
+                "build",
+                "dist",
+                "poetry.lock",
+                "Pipfile.lock",
+                "uv.lock",
+            ],
+            Preset::Go => &["vendor", "go.sum"],
+            Preset::Java => &["target", "build", "out", ".gradle", "*.class", "*.jar"],
+            Preset::Auto => &[],
+        }
+    }
+
+    /// Extensions to include.
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Rust => &["rs", "toml", "md"],
+            Preset::Node => &[
+                "js", "mjs", "cjs", "jsx", "ts", "mts", "tsx", "vue", "svelte", "json", "html", "css", "scss", "md",
+            ],
+            Preset::Python => &["py", "pyi", "toml", "cfg", "ini", "txt", "md", "rst"],
+            Preset::Go => &["go", "mod", "md"],
+            Preset::Java => &["java", "kt", "kts", "gradle", "xml", "properties", "md"],
+            Preset::Auto => &[],
+        }
+    }
+
+    /// Replaces `auto` with the presets detected in the input paths.
+    ///
+    /// Markers are looked up in each input directory, or in the parent
+    /// directory of an input file. Duplicates are removed, keeping the first.
+    pub fn resolve(presets: &[Preset], paths: &[String]) -> Vec<Preset> {
+        let mut resolved = Vec::new();
+
+        for preset in presets {
+            let expanded = match preset {
+                Preset::Auto => detect(paths),
+                preset => vec![*preset],
+            };
+
+            for preset in expanded {
+                if !resolved.contains(&preset) {
+                    resolved.push(preset);
+                }
+            }
+        }
+
+        resolved
+    }
+
+    /// Builds the settings layer for a set of resolved presets.
+    pub fn settings(presets: &[Preset]) -> Settings {
+        let mut settings = Settings::default();
+
+        for preset in presets {
+            for pattern in preset.ignore() {
+                if !settings.ignore.iter().any(|p| p == pattern) {
+                    settings.ignore.push(pattern.to_string());
+                }
+            }
+            for ext in preset.extensions() {
+                if !settings.extensions.iter().any(|e| e == ext) {
+                    settings.extensions.push(ext.to_string());
+                }
+            }
+        }
+
+        settings
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Preset::Rust => "rust",
+            Preset::Node => "node",
+            Preset::Python => "python",
+            Preset::Go => "go",
+            Preset::Java => "java",
+            Preset::Auto => "auto",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Detects presets from marker files in the input paths.
+fn detect(paths: &[String]) -> Vec<Preset> {
+    let dirs: Vec<&Path> = paths
+        .iter()
+        .map(Path::new)
+        .filter_map(|path| if path.is_dir() { Some(path) } else { path.parent() })
+        .map(|dir| if dir.as_os_str().is_empty() { Path::new(".") } else { dir })
+        .collect();
+
+    DETECTABLE
+        .iter()
+        .copied()
+        .filter(|preset| {
+            dirs.iter()
+                .any(|dir| preset.markers().iter().any(|marker| dir.join(marker).is_file()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_settings() {
+        let settings = Preset::settings(&[Preset::Rust, Preset::Java]);
+
+        assert_eq!(settings.ignore, vec!["target", "Cargo.lock", "build", "out", ".gradle", "*.class", "*.jar"]);
+        assert_eq!(settings.extensions[..4], ["rs", "toml", "md", "java"]);
+        assert_eq!(settings.extensions.iter().filter(|e| *e == "md").count(), 1);
+    }
+
+    #[test]
+    fn test_auto_detection() {
+        let dir = TempDir::new("preset");
+        fs::create_dir_all(dir.join("web")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(dir.join("web/package.json"), "{}\n").unwrap();
+        fs::write(dir.join("web/index.ts"), "\n").unwrap();
+
+        let root = dir.to_string_lossy().to_string();
+        let web_file = dir.join("web/index.ts").to_string_lossy().to_string();
+
+        assert_eq!(Preset::resolve(&[Preset::Auto], std::slice::from_ref(&root)), vec![Preset::Rust]);
+        assert_eq!(Preset::resolve(&[Preset::Auto], &[web_file]), vec![Preset::Node]);
+        assert_eq!(
+            Preset::resolve(&[Preset::Rust, Preset::Auto, Preset::Go], &[root]),
+            vec![Preset::Rust, Preset::Go],
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Single-read file access.
+//!
+//! A `FileProbe` opens a file once and caches what has been read from it.
+//! Validation and detection only look at a leading sample; decoding reads
+//! the remainder from the same handle, so every byte is read at most once.
+//! When a UTF-8 sample turns out not to cover the whole file, the encoding is
+//! detected again from the cached contents.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+
+use crate::detector::{Encoding, FileDetector, FileType, BINARY_CHECK_BYTES};
+
+/// An open file with its metadata and the bytes read from it so far.
+pub struct FileProbe {
+    file: File,
+    size: u64,
+    /// The leading sample once loaded, and the whole file once complete
+    bytes: Vec<u8>,
+    sampled: bool,
+    complete: bool,
+}
+
+impl FileProbe {
+    /// Opens a regular file for probing.
+    ///
+    /// Paths that do not exist or are not regular files fail with `NotFound`.
+    /// The type is checked before opening, so special files are never opened.
+    pub fn open(path: &str) -> Result<Self, io::Error> {
+        let metadata = fs::metadata(path)?;
+        if !metadata.is_file() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "not a regular file"));
+        }
+
+        Ok(Self {
+            file: File::open(path)?,
+            size: metadata.len(),
+            bytes: Vec::new(),
+            sampled: false,
+            complete: false,
+        })
+    }
+
+    /// Returns the file size reported by its metadata.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the leading bytes of the file, reading them on first use.
+    pub fn sample(&mut self) -> Result<&[u8], io::Error> {
+        if !self.sampled {
+            (&mut self.file).take(BINARY_CHECK_BYTES as u64).read_to_end(&mut self.bytes)?;
+            self.sampled = true;
+        }
+
+        Ok(&self.bytes[..self.bytes.len().min(BINARY_CHECK_BYTES)])
+    }
+
+    /// Classifies the file as text or binary from its sample.
+    pub fn file_type(&mut self) -> Result<FileType, io::Error> {
+        let truncated = self.is_truncated()?;
+        Ok(FileDetector::classify_sample(self.sample()?, truncated))
+    }
+
+    /// Detects the encoding of the file from its sample.
+    pub fn encoding(&mut self) -> Result<Encoding, io::Error> {
+        let truncated = self.is_truncated()?;
+        Ok(FileDetector::detect_sample_encoding(self.sample()?, truncated))
+    }
+
+    /// Returns the entire contents of the file, reading what the sample left out.
+    pub fn contents(&mut self) -> Result<&[u8], io::Error> {
+        self.sample()?;
+        if !self.complete {
+            self.bytes.reserve((self.size as usize).saturating_sub(self.bytes.len()));
+            self.file.read_to_end(&mut self.bytes)?;
+            self.complete = true;
+        }
+
+        Ok(&self.bytes)
+    }
+
+    /// Reads the entire file and decodes it into a UTF-8 string.
+    ///
+    /// The encoding is detected from the sample. A sample that passes as UTF-8
+    /// may be an ASCII header in front of legacy text, so when the whole file
+    /// fails to decode as UTF-8 the encoding is detected again from all of it.
+    pub fn read_text(&mut self) -> Result<(Encoding, String), io::Error> {
+        let encoding = self.encoding()?;
+        match encoding.decode(self.contents()?) {
+            Err(e) if encoding == Encoding::Utf8 && e.kind() == io::ErrorKind::InvalidData => {
+                let contents = self.contents()?;
+                let encoding = FileDetector::detect_sample_encoding(contents, false);
+                let text = encoding.decode(contents)?;
+                Ok((encoding, text))
+            }
+            result => result.map(|text| (encoding, text)),
+        }
+    }
+
+    /// Whether the file continues past its sample.
+    fn is_truncated(&mut self) -> Result<bool, io::Error> {
+        let sampled = self.sample()?.len();
+        Ok(if self.complete { self.bytes.len() > sampled } else { self.size > sampled as u64 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_probe_reads_once() {
+        let dir = TempDir::new("probe");
+        let text = "日本語のテキスト\n".repeat(200);
+        let path = dir.join("long.txt");
+        fs::write(&path, &text).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let mut probe = FileProbe::open(&path).unwrap();
+        assert_eq!(probe.size(), text.len() as u64);
+        assert_eq!(probe.sample().unwrap(), &text.as_bytes()[..BINARY_CHECK_BYTES]);
+        assert_eq!(probe.file_type().unwrap(), FileType::Text);
+        assert_eq!(probe.encoding().unwrap(), Encoding::Utf8);
+
+        // The sample is not read again; only the remainder comes from disk.
+        let rewritten = "x".repeat(text.len());
+        fs::write(&path, &rewritten).unwrap();
+        let contents = probe.contents().unwrap().to_vec();
+        assert_eq!(&contents[..BINARY_CHECK_BYTES], &text.as_bytes()[..BINARY_CHECK_BYTES]);
+        assert_eq!(&contents[BINARY_CHECK_BYTES..], &rewritten.as_bytes()[BINARY_CHECK_BYTES..]);
+
+        // Once complete, the cached contents are returned without reading.
+        fs::write(&path, "changed\n").unwrap();
+        assert_eq!(probe.contents().unwrap(), contents);
+        assert_eq!(probe.sample().unwrap(), &text.as_bytes()[..BINARY_CHECK_BYTES]);
+
+        let dir_path = dir.to_string_lossy().to_string();
+        let error = FileProbe::open(&dir_path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_encoding_past_sample() {
+        let dir = TempDir::new("probe-late");
+        let header = "/* Licensed under the MIT license. */\n".repeat(60);
+        assert!(header.len() > BINARY_CHECK_BYTES);
+
+        let text = format!("{}// これは日本語のコメントです。\nint main(void) {{ return 0; }}\n", header);
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode(&text);
+        let path = dir.join("sjis.c");
+        fs::write(&path, &sjis).unwrap();
+
+        let mut probe = FileProbe::open(&path.to_string_lossy()).unwrap();
+        assert_eq!(probe.encoding().unwrap(), Encoding::Utf8);
+        assert_eq!(probe.read_text().unwrap(), (Encoding::ShiftJis, text));
+
+        let latin1 = [header.as_bytes(), b"caf\xE9 na\xEFve\n"].concat();
+        let path = dir.join("latin1.txt");
+        fs::write(&path, &latin1).unwrap();
+
+        let mut probe = FileProbe::open(&path.to_string_lossy()).unwrap();
+        let (encoding, text) = probe.read_text().unwrap();
+        assert_eq!(encoding, Encoding::Windows1252);
+        assert!(text.ends_with("café naïve\n"));
+    }
+}
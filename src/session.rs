@@ -0,0 +1,578 @@
+//! Processing of input paths into rendered output.
+//!
+//! A `Session` walks each input, turns files into records and passes them to
+//! the renderer, applying the total size limit and the token budget on the
+//! way. Totals and traversal errors are collected for the final summary, and
+//! inputs that could not be processed are collected for the report; nothing
+//! is printed.
+//!
+//! The token budget covers the rendered output: headers, trees and the
+//! summary count against it along with the file contents.
+
+use std::cell::Cell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::budget::{self, Decision};
+use crate::config::Config;
+use crate::destination::OutputFiles;
+use crate::detector::FileType;
+use crate::gitignore::IgnoreRules;
+use crate::io;
+use crate::output::{self, FileOutcome, FileRecord, RenderOptions, Renderer, Summary};
+use crate::pool;
+use crate::probe::FileProbe;
+use crate::stats::Stats;
+use crate::tokens::{TokenCountingWriter, Tokenizer};
+use crate::tree::{TreeEntry, TreeMode};
+use crate::validator::{FileValidator, ValidationError};
+use crate::{Error, Report};
+
+/// Output state shared across all processed paths.
+pub(crate) struct Session<'a> {
+    /// Renderer for the selected output format
+    renderer: Box<dyn Renderer + 'a>,
+    /// Totals for the run
+    summary: Summary,
+    /// Token accounting, if a budget is set
+    budget: Option<Budget<'a>>,
+    /// Maximum total size of file contents, if set
+    max_total_size: Option<u64>,
+    /// Size of the file contents emitted so far
+    output_bytes: u64,
+    /// Directories scanned across all directory inputs
+    directories: usize,
+    /// Directories skipped by ignore rules and filters across all directory inputs
+    pruned: usize,
+    /// Lower bound on the time saved by skipping those directories
+    pruning_saved: Duration,
+    /// Symlinks that were not followed, with the reason
+    refused_links: Vec<(String, String)>,
+    /// Input paths that could not be processed, with the reason
+    input_errors: Vec<(String, String)>,
+}
+
+impl<'a> Session<'a> {
+    /// Creates a session that renders to `writer` in the configured format.
+    pub(crate) fn new<W: Write + 'a>(config: &Config, writer: W) -> Self {
+        let options = RenderOptions {
+            verbose: config.verbose,
+            tree: config.tree,
+        };
+
+        let (renderer, budget) = match (config.max_tokens, config.tokenizer) {
+            (Some(max), Some(tokenizer)) => {
+                let (writer, spent) = TokenCountingWriter::new(writer, tokenizer);
+                let (sink, measured) = TokenCountingWriter::new(std::io::sink(), tokenizer);
+                let budget = Budget {
+                    max,
+                    spent,
+                    scratch: output::create_renderer(config.format, sink, options),
+                    measured,
+                };
+                (output::create_renderer(config.format, writer, options), Some(budget))
+            }
+            _ => (output::create_renderer(config.format, writer, options), None),
+        };
+
+        Self {
+            renderer,
+            summary: Summary {
+                tokenizer: config.tokenizer,
+                max_tokens: config.max_tokens,
+                stats: config.stats.then(Stats::default),
+                ..Summary::default()
+            },
+            budget,
+            max_total_size: config.max_total_size,
+            output_bytes: 0,
+            directories: 0,
+            pruned: 0,
+            pruning_saved: Duration::ZERO,
+            refused_links: Vec::new(),
+            input_errors: Vec::new(),
+        }
+    }
+
+    /// Processes every input path, finishes the output and reports on the run.
+    pub(crate) fn run(mut self, config: &Config) -> Result<Report, Error> {
+        let started = Instant::now();
+
+        for (index, path) in config.paths.iter().enumerate() {
+            process_single_path(index, path, config, &mut self)?;
+        }
+        if let Some(stats) = &mut self.summary.stats {
+            stats.directories = self.directories;
+            stats.pruned = self.pruned;
+            stats.pruning_saved = self.pruning_saved;
+            stats.elapsed = started.elapsed();
+        }
+        self.renderer.finish(&self.summary)?;
+
+        Ok(Report {
+            summary: self.summary,
+            directories: self.directories,
+            pruned: self.pruned,
+            pruning_saved: self.pruning_saved,
+            refused_links: self.refused_links,
+            input_errors: self.input_errors,
+            elapsed: started.elapsed(),
+            parts: Vec::new(),
+        })
+    }
+
+    /// Records a processed file and passes it to the renderer.
+    ///
+    /// Text files that would push the output past the total size limit are
+    /// emitted as skipped instead.
+    fn emit(&mut self, mut record: FileRecord) -> std::io::Result<()> {
+        if let FileOutcome::Text { contents, .. } = &record.outcome {
+            let size = contents.len() as u64;
+            match self.max_total_size {
+                Some(limit) if self.output_bytes + size > limit => {
+                    record.tokens = None;
+                    record.outcome = FileOutcome::Skipped(ValidationError::OutputLimitReached { limit });
+                }
+                _ => self.output_bytes += size,
+            }
+        }
+
+        self.summary.record(&record);
+        self.renderer.file(&record)
+    }
+
+    /// Starts an input path in the output, and in the budget's scratch output.
+    fn begin_input(&mut self, index: usize, base_path: &Path, is_dir: bool) -> std::io::Result<()> {
+        if let Some(budget) = &mut self.budget {
+            budget.scratch.begin_input(index, base_path, is_dir)?;
+        }
+        self.renderer.begin_input(index, base_path, is_dir)
+    }
+
+    /// Passes a directory tree to the renderer, and to the budget's scratch
+    /// renderer for formats that only write it with the summary.
+    fn tree(&mut self, base_path: &Path, entries: &[TreeEntry]) -> std::io::Result<()> {
+        if let Some(budget) = &mut self.budget {
+            budget.scratch.tree(base_path, entries)?;
+        }
+        self.renderer.tree(base_path, entries)
+    }
+
+    /// Adds a finished directory walk to the totals.
+    fn walked(&mut self, walker: &io::Walker) {
+        self.directories += walker.directories();
+        self.pruned += walker.pruned();
+        self.pruning_saved += walker.pruning_saved();
+        self.refused_links.extend_from_slice(walker.refused_links());
+    }
+
+    /// Records a traversal error, or stops the run with it in strict mode.
+    fn walk_error(&mut self, error: io::WalkError, config: &Config) -> Result<(), Error> {
+        if config.strict {
+            return Err(Error::Traversal(error));
+        }
+
+        self.summary.traversal_errors.push((error.path, error.error.to_string()));
+        Ok(())
+    }
+}
+
+/// Token accounting for a run with a token budget.
+struct Budget<'a> {
+    /// Maximum number of tokens in the whole output
+    max: usize,
+    /// Tokens written to the output so far
+    spent: Rc<Cell<usize>>,
+    /// Renderer in the same format that discards its output, used to
+    /// measure records and the summary before they are written
+    scratch: Box<dyn Renderer + 'a>,
+    /// Tokens rendered by `scratch` so far
+    measured: Rc<Cell<usize>>,
+}
+
+impl Budget<'_> {
+    /// Returns how many tokens `render` produces in the output format.
+    fn measure(&mut self, render: impl FnOnce(&mut dyn Renderer) -> std::io::Result<()>) -> std::io::Result<usize> {
+        let before = self.measured.get();
+        render(self.scratch.as_mut())?;
+        Ok(self.measured.get() - before)
+    }
+
+    /// Plans which records fit in what is left of the budget, reserving
+    /// room for the summary the decisions lead to.
+    ///
+    /// # Arguments
+    /// * `candidates` - `(rank, rendered tokens)` for each record
+    /// * `records` - The records, in output order
+    /// * `summary` - Totals for the records already written
+    fn plan(
+        &mut self,
+        candidates: &[(usize, usize)],
+        records: &[FileRecord],
+        summary: &Summary,
+    ) -> std::io::Result<Vec<Decision>> {
+        let mut reserve = 0;
+        loop {
+            let available = self.max.saturating_sub(self.spent.get() + reserve);
+            let decisions = budget::plan(candidates, available);
+
+            let mut projected = summary.clone();
+            for (record, decision) in records.iter().zip(&decisions) {
+                let tokens = record.tokens.unwrap_or(0);
+                match decision {
+                    Decision::Include => projected.record(record),
+                    Decision::Truncate(_) => {
+                        projected.record(record);
+                        projected.truncated.push((record.path.clone(), tokens));
+                    }
+                    Decision::Drop => projected.dropped.push((record.path.clone(), tokens)),
+                }
+            }
+
+            let needed = self.measure(|scratch| scratch.finish(&projected))?;
+            if needed <= reserve {
+                return Ok(decisions);
+            }
+            reserve = needed;
+        }
+    }
+}
+
+/// Processes a single path (file or directory).
+///
+/// # Arguments
+/// * `index` - Position of the path among the inputs
+/// * `path` - The path to process
+/// * `config` - Application configuration
+/// * `session` - Output state
+fn process_single_path(index: usize, path: &str, config: &Config, session: &mut Session) -> Result<(), Error> {
+    let base_path = match std::fs::canonicalize(path) {
+        Ok(p) => p,
+        Err(e) => {
+            session.input_errors.push((path.to_string(), format!("cannot resolve path: {}", e)));
+            return Ok(());
+        }
+    };
+
+    match io::check_path_type(path) {
+        Ok(io::PathType::Directory) => {
+            session.begin_input(index, &base_path, true)?;
+            process_directory(path, &base_path, config, session)?;
+        }
+        Ok(io::PathType::File) => {
+            session.begin_input(index, &base_path, false)?;
+            let name = base_path.file_name().map_or_else(String::new, |n| n.to_string_lossy().to_string());
+            if !config.filter.should_process_input(&name) {
+                return Ok(());
+            }
+
+            let file = InputFile {
+                path: path.to_string(),
+                relative: name,
+                symlink: std::fs::read_link(path).ok(),
+                refused: None,
+            };
+
+            if config.tree == TreeMode::Only {
+                let parent = base_path.parent().unwrap_or(&base_path);
+                let entry = TreeEntry {
+                    path: file.relative,
+                    size: std::fs::metadata(path).ok().map(|m| m.len()),
+                    symlink: file.symlink.map(|target| target.display().to_string()),
+                };
+                session.tree(parent, &[entry])?;
+            } else {
+                process_files(&[file], true, config, session)?;
+            }
+        }
+        Ok(io::PathType::Other) => {
+            session.input_errors.push((path.to_string(), "unsupported path type".to_string()));
+        }
+        Err(e) => {
+            session.input_errors.push((path.to_string(), format!("cannot access path: {}", e)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Processes a directory recursively.
+///
+/// # Arguments
+/// * `path` - Directory path to process
+/// * `base_path` - Base path for relative path calculation
+/// * `config` - Application configuration
+/// * `session` - Output state
+fn process_directory(path: &str, base_path: &Path, config: &Config, session: &mut Session) -> Result<(), Error> {
+    let ignore_rules = config.use_ignore_files.then(|| IgnoreRules::new(base_path));
+    let output_files = config.output.as_deref().and_then(OutputFiles::new);
+
+    let keep = |entry: &io::DirectoryEntry| {
+        if !entry.is_directory && output_files.as_ref().is_some_and(|output| output.contains(Path::new(&entry.path))) {
+            return false;
+        }
+
+        let relative = relative_display(&entry.path, path);
+        let ignored = ignore_rules
+            .as_ref()
+            .is_some_and(|rules| rules.is_ignored(Path::new(&relative), entry.is_directory));
+        !ignored && config.filter.should_process(&relative, entry.is_directory)
+    };
+
+    let mut walker = io::Walker::new(path, Some(config.max_depth), config.follow_symlinks, &keep);
+
+    // Stream files as they are found unless something needs the whole list first
+    let needs_all_files = config.tree == TreeMode::Only
+        || session.renderer.wants_tree()
+        || session.budget.is_some();
+
+    if !needs_all_files {
+        let work = |entry: Result<io::DirectoryEntry, io::WalkError>| {
+            entry.map(|entry| build_record(&InputFile::from_entry(entry, path), false, config))
+        };
+        pool::map_ordered(walker.by_ref(), config.jobs, work, |result| match result {
+            Ok(record) => Ok(session.emit(record)?),
+            Err(e) => session.walk_error(e, config),
+        })?;
+        session.walked(&walker);
+        return Ok(());
+    }
+
+    let mut files = Vec::new();
+    let mut tree_entries = Vec::new();
+    for entry in walker.by_ref() {
+        match entry {
+            Ok(entry) => {
+                let size = if entry.refused.is_some() { None } else { entry.size };
+                let file = InputFile::from_entry(entry, path);
+                tree_entries.push(TreeEntry {
+                    path: file.relative.clone(),
+                    size,
+                    symlink: file.symlink.as_ref().map(|target| target.display().to_string()),
+                });
+                files.push(file);
+            }
+            Err(e) => session.walk_error(e, config)?,
+        }
+    }
+    session.walked(&walker);
+
+    for (link, target) in walker.linked_directories() {
+        tree_entries.push(TreeEntry {
+            path: format!("{}/", relative_display(link, path)),
+            size: None,
+            symlink: Some(target.display().to_string()),
+        });
+    }
+    session.tree(base_path, &tree_entries)?;
+
+    if config.tree == TreeMode::Only {
+        return Ok(());
+    }
+
+    Ok(process_files(&files, false, config, session)?)
+}
+
+/// A file selected for output.
+struct InputFile {
+    /// Path to read the file from
+    path: String,
+    /// Path relative to the input directory, using `/` separators
+    relative: String,
+    /// Target of the file if it is a symlink
+    symlink: Option<PathBuf>,
+    /// Why the file, a symlink, is shown without being followed
+    refused: Option<String>,
+}
+
+impl InputFile {
+    /// Creates an input file from a walked entry below the directory input `dir`.
+    fn from_entry(entry: io::DirectoryEntry, dir: &str) -> Self {
+        Self {
+            relative: relative_display(&entry.path, dir),
+            path: entry.path,
+            symlink: entry.symlink,
+            refused: entry.refused,
+        }
+    }
+}
+
+/// Returns a walked file's path relative to the directory input, using `/` separators.
+fn relative_display(file: &str, dir: &str) -> String {
+    let relative = Path::new(file).strip_prefix(dir).unwrap_or(Path::new(file));
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Processes a list of files, applying the token budget if one is set.
+///
+/// # Arguments
+/// * `files` - Files in output order
+/// * `is_root` - Whether these are root files (affects display formatting)
+/// * `config` - Application configuration
+/// * `session` - Output state
+fn process_files(
+    files: &[InputFile],
+    is_root: bool,
+    config: &Config,
+    session: &mut Session,
+) -> std::io::Result<()> {
+    let work = |file: &InputFile| build_record(file, is_root, config);
+
+    let Some(budget) = session.budget.as_mut() else {
+        return pool::map_ordered(files.iter(), config.jobs, work, |record| session.emit(record));
+    };
+
+    let mut records: Vec<FileRecord> = Vec::with_capacity(files.len());
+    pool::map_ordered(files.iter(), config.jobs, work, |record| {
+        records.push(record);
+        Ok::<(), std::io::Error>(())
+    })?;
+
+    let mut candidates = Vec::with_capacity(records.len());
+    for (file, record) in files.iter().zip(&records) {
+        let cost = budget.measure(|scratch| scratch.file(record))?;
+        candidates.push((config.priority.rank(&file.relative), cost));
+    }
+
+    let decisions = budget.plan(&candidates, &records, &session.summary)?;
+    let tokenizer = config.tokenizer.unwrap_or(Tokenizer::Cl100k);
+
+    for ((mut record, decision), (_, cost)) in records.into_iter().zip(decisions).zip(candidates) {
+        let tokens = record.tokens.unwrap_or(0);
+
+        match decision {
+            Decision::Include => {}
+            Decision::Truncate(limit) if truncate_record(&mut record, limit, cost, tokenizer, session)? => {
+                session.summary.truncated.push((record.path.clone(), tokens));
+            }
+            Decision::Truncate(_) | Decision::Drop => {
+                session.summary.dropped.push((record.path, tokens));
+                continue;
+            }
+        }
+
+        session.emit(record)?;
+    }
+
+    Ok(())
+}
+
+/// Shortens a text record so that its rendered output fits in `limit` tokens.
+///
+/// Returns false if even the shortest excerpt does not fit.
+///
+/// # Arguments
+/// * `record` - The record to shorten
+/// * `limit` - Tokens the rendered record may take
+/// * `cost` - Tokens the whole record takes when rendered
+/// * `tokenizer` - Tokenizer used to count the contents
+/// * `session` - Output state, used to measure the result
+fn truncate_record(
+    record: &mut FileRecord,
+    limit: usize,
+    cost: usize,
+    tokenizer: Tokenizer,
+    session: &mut Session,
+) -> std::io::Result<bool> {
+    let (FileOutcome::Text { contents, .. }, Some(budget)) = (&record.outcome, session.budget.as_mut()) else {
+        return Ok(false);
+    };
+
+    let original = contents.clone();
+    let omitted_before = record.omitted_lines.unwrap_or(0);
+    let mut content_limit = limit.saturating_sub(cost.saturating_sub(record.tokens.unwrap_or(0)));
+
+    // The overhead can change with the shortened contents, so measure again
+    loop {
+        let (truncated, truncated_tokens, omitted) = budget::truncate_to_tokens(&original, content_limit, tokenizer);
+        if let FileOutcome::Text { contents, .. } = &mut record.outcome {
+            *contents = truncated;
+        }
+        record.tokens = Some(truncated_tokens);
+        record.omitted_lines = Some(omitted_before + omitted);
+
+        let rendered = budget.measure(|scratch| scratch.file(record))?;
+        if rendered <= limit {
+            return Ok(true);
+        }
+        if content_limit == 0 {
+            return Ok(false);
+        }
+        content_limit = content_limit.saturating_sub(rendered - limit);
+    }
+}
+
+/// Processes a single file into a record.
+///
+/// # Arguments
+/// * `file` - File to process
+/// * `is_root` - Whether this is a root file (affects display formatting)
+/// * `config` - Application configuration
+fn build_record(file: &InputFile, is_root: bool, config: &Config) -> FileRecord {
+    let (size, mut outcome) = match &file.refused {
+        Some(reason) => (None, FileOutcome::Symlink(reason.clone())),
+        None => match FileProbe::open(&file.path) {
+            Ok(mut probe) => (Some(probe.size()), inspect_file(&mut probe, config.max_file_size)),
+            Err(e) => (None, FileOutcome::Skipped(e.into())),
+        },
+    };
+    let mut omitted_lines = None;
+
+    if let (FileOutcome::Text { contents, .. }, Some(excerpt)) = (&mut outcome, config.excerpt) {
+        if let Some((shortened, omitted)) = excerpt.apply(contents) {
+            *contents = shortened;
+            omitted_lines = Some(omitted);
+        }
+    }
+
+    let tokens = match (&outcome, config.tokenizer) {
+        (FileOutcome::Text { contents, .. }, Some(tokenizer)) => Some(tokenizer.count(contents)),
+        _ => None,
+    };
+
+    FileRecord {
+        path: display_name(file, is_root),
+        is_root,
+        size,
+        symlink: file.symlink.as_ref().map(|target| target.display().to_string()),
+        tokens,
+        omitted_lines,
+        outcome,
+    }
+}
+
+/// Validates, classifies and reads a file.
+fn inspect_file(probe: &mut FileProbe, max_file_size: u64) -> FileOutcome {
+    if let Err(e) = FileValidator::quick_validate(probe, max_file_size) {
+        return FileOutcome::Skipped(e);
+    }
+
+    match probe.file_type() {
+        Ok(FileType::Binary) => FileOutcome::Binary,
+        Ok(FileType::Text) => {
+            match probe.read_text() {
+                Ok((encoding, contents)) => FileOutcome::Text { encoding, contents },
+                Err(e) => FileOutcome::ReadError(e),
+            }
+        }
+        Err(e) => FileOutcome::DetectionError(e),
+    }
+}
+
+/// Formats the display name for a file.
+///
+/// Root files keep the path as given; files found inside a directory are
+/// shown relative to it with a leading `./`.
+fn display_name(file: &InputFile, is_root: bool) -> String {
+    if is_root {
+        file.path.clone()
+    } else {
+        format!("./{}", file.relative)
+    }
+}
@@ -0,0 +1,474 @@
+//! Configuration files.
+//!
+//! Settings are layered: a user-level `config.toml` in the XDG config
+//! directory, then a project `vitax.toml` (or `.vitaxrc`) found by searching
+//! upward from the working directory, then the command line. Values from later
+//! layers override earlier ones, except ignore patterns, which accumulate.
+//! Files may also define named `[profile.<name>]` tables that are applied on
+//! top of the file settings when selected with `--profile`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer};
+
+use crate::config::ConfigError;
+use crate::io::FollowSymlinks;
+use crate::output::OutputFormat;
+use crate::preset::Preset;
+use crate::tokens::Tokenizer;
+use crate::units::parse_size;
+
+/// Project configuration file names, in lookup order.
+const PROJECT_FILES: &[&str] = &["vitax.toml", ".vitaxrc"];
+
+/// One layer of settings, from a configuration file or the command line.
+///
+/// Unset values leave the value of the layer below unchanged.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    /// Language and ecosystem presets
+    #[serde(deserialize_with = "value_enums")]
+    pub preset: Vec<Preset>,
+    /// Patterns to ignore
+    pub ignore: Vec<String>,
+    /// File extensions to include
+    #[serde(alias = "ext")]
+    pub extensions: Vec<String>,
+    /// Glob patterns for relative paths to include
+    pub include: Vec<String>,
+    /// Maximum recursion depth
+    pub depth: Option<usize>,
+    /// Show hidden files and directories
+    pub all: Option<bool>,
+    /// Do not respect .gitignore, .ignore and git exclude files
+    pub no_ignore: Option<bool>,
+    /// Output format
+    #[serde(deserialize_with = "value_enum")]
+    pub format: Option<OutputFormat>,
+    /// Print a directory tree before the file contents
+    pub tree: Option<bool>,
+    /// Print only the directory tree
+    pub tree_only: Option<bool>,
+    /// Tokenizer for per-file token counts
+    #[serde(deserialize_with = "value_enum")]
+    pub tokens: Option<Tokenizer>,
+    /// Do not count tokens per file, even if a layer below sets a tokenizer
+    pub no_tokens: Option<bool>,
+    /// Maximum size of a single file in bytes
+    #[serde(deserialize_with = "size")]
+    pub max_size: Option<u64>,
+    /// Maximum total size of file contents in bytes
+    #[serde(deserialize_with = "size")]
+    pub max_total_size: Option<u64>,
+    /// Head and tail excerpt length for long files
+    pub max_lines: Option<usize>,
+    /// Lines to keep from the beginning of long files
+    pub head: Option<usize>,
+    /// Lines to keep from the end of long files
+    pub tail: Option<usize>,
+    /// Token budget for the whole output
+    pub max_tokens: Option<usize>,
+    /// Glob patterns to prioritize under the token budget
+    pub priority: Vec<String>,
+    /// Which symlinks to follow
+    #[serde(deserialize_with = "value_enum")]
+    pub follow_symlinks: Option<FollowSymlinks>,
+    /// Do not follow symlinks, even if a layer below follows them
+    pub no_follow_symlinks: Option<bool>,
+    /// Number of worker threads
+    pub jobs: Option<usize>,
+    /// File to write the output to
+    pub output: Option<PathBuf>,
+    /// Maximum size of each output part in bytes
+    #[serde(deserialize_with = "size")]
+    pub split_size: Option<u64>,
+    /// Maximum number of tokens in each output part
+    pub split_tokens: Option<usize>,
+    /// Collect statistics by outcome and language
+    pub stats: Option<bool>,
+    /// Stop at the first unreadable directory or entry
+    pub strict: Option<bool>,
+    /// Verbose output mode
+    pub verbose: Option<bool>,
+}
+
+impl Settings {
+    /// Layers `other` on top of these settings.
+    ///
+    /// Ignore patterns accumulate; lists such as extensions and includes are replaced when
+    /// `other` sets any. Setting either tree option, either kind of line
+    /// limit, or a value or its negation, as with `tokens` and `no-tokens`,
+    /// replaces both values of the pair.
+    pub fn merge(self, other: Settings) -> Settings {
+        let (tree, tree_only) = if other.tree.is_some() || other.tree_only.is_some() {
+            (other.tree, other.tree_only)
+        } else {
+            (self.tree, self.tree_only)
+        };
+
+        let (tokens, no_tokens) = if other.tokens.is_some() || other.no_tokens.is_some() {
+            (other.tokens, other.no_tokens)
+        } else {
+            (self.tokens, self.no_tokens)
+        };
+
+        let (follow_symlinks, no_follow_symlinks) =
+            if other.follow_symlinks.is_some() || other.no_follow_symlinks.is_some() {
+                (other.follow_symlinks, other.no_follow_symlinks)
+            } else {
+                (self.follow_symlinks, self.no_follow_symlinks)
+            };
+
+        let (max_lines, head, tail) = if other.max_lines.is_some() {
+            (other.max_lines, None, None)
+        } else if other.head.is_some() || other.tail.is_some() {
+            (None, other.head, other.tail)
+        } else {
+            (self.max_lines, self.head, self.tail)
+        };
+
+        Settings {
+            preset: if other.preset.is_empty() { self.preset } else { other.preset },
+            ignore: self.ignore.into_iter().chain(other.ignore).collect(),
+            extensions: if other.extensions.is_empty() { self.extensions } else { other.extensions },
+            include: if other.include.is_empty() { self.include } else { other.include },
+            depth: other.depth.or(self.depth),
+            all: other.all.or(self.all),
+            no_ignore: other.no_ignore.or(self.no_ignore),
+            format: other.format.or(self.format),
+            tree,
+            tree_only,
+            tokens,
+            no_tokens,
+            max_size: other.max_size.or(self.max_size),
+            max_total_size: other.max_total_size.or(self.max_total_size),
+            max_lines,
+            head,
+            tail,
+            max_tokens: other.max_tokens.or(self.max_tokens),
+            priority: if other.priority.is_empty() { self.priority } else { other.priority },
+            follow_symlinks,
+            no_follow_symlinks,
+            jobs: other.jobs.or(self.jobs),
+            output: other.output.or(self.output),
+            split_size: other.split_size.or(self.split_size),
+            split_tokens: other.split_tokens.or(self.split_tokens),
+            stats: other.stats.or(self.stats),
+            strict: other.strict.or(self.strict),
+            verbose: other.verbose.or(self.verbose),
+        }
+    }
+}
+
+/// The contents of a configuration file: top-level settings and named profiles.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfigFile {
+    /// Settings that always apply
+    pub settings: Settings,
+    /// Settings from `[profile.<name>]` tables, applied with `--profile <name>`
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+impl ConfigFile {
+    /// Parses the contents of a configuration file.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut table: toml::Table = toml::from_str(text).map_err(|e| e.message().to_string())?;
+
+        let profiles = match table.remove("profile") {
+            Some(profiles) => profiles
+                .try_into()
+                .map_err(|e: toml::de::Error| format!("in [profile]: {}", e.message()))?,
+            None => BTreeMap::new(),
+        };
+        let settings = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| e.message().to_string())?;
+
+        Ok(Self { settings, profiles })
+    }
+
+    /// Reads a configuration file.
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::ConfigFile(path.display().to_string(), e.to_string()))?;
+
+        Self::parse(&text).map_err(|e| ConfigError::ConfigFile(path.display().to_string(), e))
+    }
+
+    /// Loads and merges the configuration files that apply to this run.
+    ///
+    /// # Arguments
+    /// * `explicit` - File given with `--config`; when set, no other file is read
+    ///
+    /// Returns the merged contents and the files they were read from.
+    pub fn load(explicit: Option<&Path>) -> Result<(Self, Vec<PathBuf>), ConfigError> {
+        let files = match explicit {
+            Some(path) => vec![path.to_path_buf()],
+            None => {
+                let cwd = std::env::current_dir().unwrap_or_default();
+                user_config_path()
+                    .filter(|path| path.is_file())
+                    .into_iter()
+                    .chain(find_project_config(&cwd))
+                    .collect()
+            }
+        };
+
+        let mut config = ConfigFile::default();
+        for path in &files {
+            config = config.merge(ConfigFile::read(path)?);
+        }
+
+        Ok((config, files))
+    }
+
+    /// Layers `other` on top of this file; profiles with the same name are merged.
+    pub fn merge(mut self, other: ConfigFile) -> ConfigFile {
+        for (name, profile) in other.profiles {
+            let merged = match self.profiles.remove(&name) {
+                Some(base) => base.merge(profile),
+                None => profile,
+            };
+            self.profiles.insert(name, merged);
+        }
+
+        ConfigFile {
+            settings: self.settings.merge(other.settings),
+            profiles: self.profiles,
+        }
+    }
+
+    /// Returns the settings with the named profile, if any, applied on top.
+    pub fn resolve(mut self, profile: Option<&str>) -> Result<Settings, ConfigError> {
+        let Some(name) = profile else {
+            return Ok(self.settings);
+        };
+
+        match self.profiles.remove(name) {
+            Some(profile) => Ok(self.settings.merge(profile)),
+            None => Err(ConfigError::UnknownProfile(
+                name.to_string(),
+                self.profiles.into_keys().collect(),
+            )),
+        }
+    }
+}
+
+/// Returns the location of the user-level configuration file.
+fn user_config_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(windows) {
+                std::env::var_os("APPDATA").map(PathBuf::from)
+            } else {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+            }
+        })?;
+
+    Some(dir.join("vitax").join("config.toml"))
+}
+
+/// Searches `start` and its ancestors for a project configuration file.
+fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find_map(|dir| {
+        PROJECT_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Deserializes a value by its command line name, such as `jsonl` or `o200k`.
+fn value_enum<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ValueEnum,
+{
+    let value = String::deserialize(deserializer)?;
+    T::from_str(&value, true).map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Deserializes one value or a list of values by their command line names.
+fn value_enums<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ValueEnum,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let values = match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    };
+
+    values
+        .iter()
+        .map(|value| T::from_str(value, true).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// Deserializes a size given either as a number of bytes or as a string like `200K`.
+fn size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Size::deserialize(deserializer)? {
+        Size::Bytes(bytes) => Ok(Some(bytes)),
+        Size::Text(text) => parse_size(&text).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_parse() {
+        let settings = ConfigFile::parse(
+            r#"
+            ignore = ["target", "node_modules"]
+            ext = ["rs", "toml"]
+            depth = 4
+            format = "md"
+            max-size = "200K"
+            max-total-size = 1048576
+            tokens = "o200k"
+            preset = "rust"
+            split-size = "500K"
+            "#,
+        )
+        .unwrap()
+        .settings;
+
+        assert_eq!(settings.ignore, vec!["target", "node_modules"]);
+        assert_eq!(settings.extensions, vec!["rs", "toml"]);
+        assert_eq!(settings.depth, Some(4));
+        assert_eq!(settings.format, Some(OutputFormat::Markdown));
+        assert_eq!(settings.max_size, Some(200 * 1024));
+        assert_eq!(settings.max_total_size, Some(1024 * 1024));
+        assert_eq!(settings.tokens, Some(Tokenizer::O200k));
+        assert_eq!(settings.preset, vec![Preset::Rust]);
+        assert_eq!(settings.split_size, Some(500 * 1024));
+
+        let settings = ConfigFile::parse("preset = [\"rust\", \"node\"]").unwrap().settings;
+        assert_eq!(settings.preset, vec![Preset::Rust, Preset::Node]);
+
+        assert!(ConfigFile::parse("format = \"html\"").is_err());
+        assert!(ConfigFile::parse("max-size = \"lots\"").is_err());
+        assert!(ConfigFile::parse("unknown = 1").is_err());
+        assert!(ConfigFile::parse("[profile.llm]\nunknown = 1").is_err());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let user = ConfigFile::parse(
+            r#"
+            ignore = ["target"]
+
+            [profile.llm]
+            format = "xml"
+            max-tokens = 50000
+            "#,
+        )
+        .unwrap();
+        let project = ConfigFile::parse(
+            r#"
+            [profile.llm]
+            ignore = ["*.lock"]
+            max-tokens = 80000
+
+            [profile.review]
+            ext = ["rs"]
+            tree = true
+            "#,
+        )
+        .unwrap();
+
+        let merged = user.merge(project);
+        assert_eq!(merged.profiles.keys().collect::<Vec<_>>(), vec!["llm", "review"]);
+
+        let llm = merged.clone().resolve(Some("llm")).unwrap();
+        assert_eq!(llm.ignore, vec!["target", "*.lock"]);
+        assert_eq!(llm.format, Some(OutputFormat::Xml));
+        assert_eq!(llm.max_tokens, Some(80000));
+
+        let plain = merged.clone().resolve(None).unwrap();
+        assert_eq!(plain.max_tokens, None);
+
+        match merged.resolve(Some("docs")) {
+            Err(ConfigError::UnknownProfile(name, available)) => {
+                assert_eq!(name, "docs");
+                assert_eq!(available, vec!["llm", "review"]);
+            }
+            other => panic!("expected unknown profile, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let file = Settings {
+            ignore: vec!["target".to_string()],
+            extensions: vec!["rs".to_string()],
+            depth: Some(3),
+            tree_only: Some(true),
+            max_lines: Some(100),
+            ..Settings::default()
+        };
+        let cli = Settings {
+            ignore: vec!["*.log".to_string()],
+            depth: Some(5),
+            tree: Some(true),
+            head: Some(20),
+            ..Settings::default()
+        };
+
+        let merged = file.merge(cli);
+        assert_eq!(merged.ignore, vec!["target", "*.log"]);
+        assert_eq!(merged.extensions, vec!["rs"]);
+        assert_eq!(merged.depth, Some(5));
+        assert_eq!((merged.tree, merged.tree_only), (Some(true), None));
+        assert_eq!((merged.max_lines, merged.head, merged.tail), (None, Some(20), None));
+
+        let file = ConfigFile::parse("tokens = \"o200k\"\nfollow-symlinks = \"all\"\n").unwrap().settings;
+        let profile = ConfigFile::parse("no-tokens = true\nno-follow-symlinks = true\n").unwrap().settings;
+        let merged = file.merge(profile);
+        assert_eq!((merged.tokens, merged.no_tokens), (None, Some(true)));
+        assert_eq!((merged.follow_symlinks, merged.no_follow_symlinks), (None, Some(true)));
+
+        let cli = Settings { tokens: Some(Tokenizer::Cl100k), ..Settings::default() };
+        assert_eq!(merged.merge(cli).no_tokens, None);
+    }
+
+    #[test]
+    fn test_find_project_config() {
+        let dir = TempDir::new("settings");
+        fs::create_dir_all(dir.join("src/nested")).unwrap();
+
+        assert_eq!(find_project_config(&dir.join("src/nested")).filter(|p| p.starts_with(&dir)), None);
+
+        fs::write(dir.join(".vitaxrc"), "depth = 2\n").unwrap();
+        assert_eq!(find_project_config(&dir.join("src/nested")), Some(dir.join(".vitaxrc")));
+
+        fs::write(dir.join("src/vitax.toml"), "depth = 3\n").unwrap();
+        assert_eq!(find_project_config(&dir.join("src/nested")), Some(dir.join("src/vitax.toml")));
+    }
+}
@@ -0,0 +1,176 @@
+//! Run statistics.
+//!
+//! Counts every processed file by outcome and by language, with the lines of
+//! text that were printed, for the `--stats` report and the JSON summary.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::language;
+use crate::output::{FileOutcome, FileRecord};
+use crate::units::{format_count, format_size};
+
+/// Files, bytes and lines for one language or extension.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageStats {
+    /// Files encountered
+    pub files: usize,
+    /// Total size of the files in bytes
+    pub bytes: u64,
+    /// Lines of text printed
+    pub lines: usize,
+}
+
+/// Statistics collected over a run.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    /// Files by outcome, keyed by `FileRecord::outcome_kind`
+    pub outcomes: BTreeMap<&'static str, usize>,
+    /// Files by language, or by extension when the language is unknown
+    pub languages: BTreeMap<String, LanguageStats>,
+    /// Total size of all files encountered in bytes
+    pub bytes: u64,
+    /// Lines of text printed
+    pub lines: usize,
+    /// Directories scanned
+    pub directories: usize,
+    /// Directories skipped by ignore rules and filters
+    pub pruned: usize,
+    /// Lower bound on the time saved by skipping directories, not counting
+    /// their subdirectories
+    pub pruning_saved: Duration,
+    /// Time the run took
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    /// Accounts for a processed file.
+    pub fn record(&mut self, record: &FileRecord) {
+        *self.outcomes.entry(record.outcome_kind()).or_default() += 1;
+
+        let size = record.size.unwrap_or(0);
+        let lines = match &record.outcome {
+            FileOutcome::Text { contents, .. } => contents.lines().count(),
+            _ => 0,
+        };
+        self.bytes += size;
+        self.lines += lines;
+
+        let language = self.languages.entry(language_key(&record.path)).or_default();
+        language.files += 1;
+        language.bytes += size;
+        language.lines += lines;
+    }
+
+    /// Returns the total number of files.
+    pub fn files(&self) -> usize {
+        self.outcomes.values().sum()
+    }
+
+    /// Returns the languages with the most files first.
+    pub fn by_language(&self) -> Vec<(&str, &LanguageStats)> {
+        let mut languages: Vec<_> = self.languages.iter().map(|(name, stats)| (name.as_str(), stats)).collect();
+        languages.sort_by(|a, b| b.1.files.cmp(&a.1.files).then_with(|| a.0.cmp(b.0)));
+        languages
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcomes: Vec<String> = self
+            .outcomes
+            .iter()
+            .map(|(kind, count)| format!("{} {}", format_count(*count), kind.replace('_', " ")))
+            .collect();
+
+        writeln!(f, "Files:    {} ({})", format_count(self.files()), format_size(self.bytes))?;
+        if !outcomes.is_empty() {
+            writeln!(f, "Outcomes: {}", outcomes.join(", "))?;
+        }
+        writeln!(f, "Lines:    {} printed", format_count(self.lines))?;
+        write!(f, "Dirs:     {} scanned, {} pruned", format_count(self.directories), format_count(self.pruned))?;
+        if self.pruned > 0 {
+            write!(f, " (at least {} ms saved, not counting their subdirectories)", self.pruning_saved.as_millis())?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Elapsed:  {} ms", self.elapsed.as_millis())?;
+
+        let languages = self.by_language();
+        if !languages.is_empty() {
+            let width = languages.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("language".len());
+            writeln!(f, "  {:<width$}  {:>7}  {:>10}  {:>10}", "language", "files", "lines", "size", width = width)?;
+            for (name, stats) in languages {
+                writeln!(
+                    f,
+                    "  {:<width$}  {:>7}  {:>10}  {:>10}",
+                    name,
+                    format_count(stats.files),
+                    format_count(stats.lines),
+                    format_size(stats.bytes),
+                    width = width,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the language of a file, or `.ext` when the language is unknown.
+fn language_key(path: &str) -> String {
+    if let Some(language) = language::language_for(path) {
+        return language.to_string();
+    }
+
+    match Path::new(path).extension() {
+        Some(ext) => format!(".{}", ext.to_string_lossy().to_lowercase()),
+        None => "(no extension)".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detector::Encoding;
+    use crate::validator::ValidationError;
+
+    fn record(path: &str, size: u64, outcome: FileOutcome) -> FileRecord {
+        FileRecord {
+            path: path.to_string(),
+            is_root: false,
+            size: Some(size),
+            tokens: None,
+            omitted_lines: None,
+            symlink: None,
+            outcome,
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let text = |contents: &str| FileOutcome::Text { encoding: Encoding::Utf8, contents: contents.to_string() };
+        let mut stats = Stats::default();
+
+        stats.record(&record("./src/main.rs", 20, text("fn main() {\n}\n")));
+        stats.record(&record("./src/lib.rs", 10, text("mod a;")));
+        stats.record(&record("./logo.PNG", 500, FileOutcome::Binary));
+        stats.record(&record("./LICENSE", 90, FileOutcome::Skipped(ValidationError::SuspiciousContent)));
+        stats.record(&record(
+            "./big.rs",
+            99,
+            FileOutcome::Skipped(ValidationError::FileTooLarge { size: 99, limit: 10 }),
+        ));
+
+        assert_eq!(stats.files(), 5);
+        assert_eq!(stats.bytes, 719);
+        assert_eq!(stats.lines, 3);
+        assert_eq!(stats.outcomes["text"], 2);
+        assert_eq!(stats.outcomes["too_large"], 1);
+        assert_eq!(stats.outcomes["suspicious_content"], 1);
+
+        let names: Vec<&str> = stats.by_language().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["rust", "(no extension)", ".png"]);
+        assert_eq!(stats.languages["rust"], LanguageStats { files: 3, bytes: 129, lines: 3 });
+    }
+}
@@ -0,0 +1,99 @@
+//! Token counting.
+//!
+//! Counts how many LLM tokens file contents occupy, either exactly with an
+//! embedded BPE vocabulary or approximately with a character-based estimate.
+
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use clap::ValueEnum;
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton};
+
+/// Average number of characters per token used by the estimator.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Supported tokenizers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Tokenizer {
+    /// cl100k_base BPE (GPT-4, GPT-3.5)
+    Cl100k,
+    /// o200k_base BPE (GPT-4o)
+    O200k,
+    /// Fast estimate of one token per four characters
+    Estimate,
+}
+
+impl Tokenizer {
+    /// Counts the tokens in a text.
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            Tokenizer::Cl100k => cl100k_base_singleton().encode_ordinary(text).len(),
+            Tokenizer::O200k => o200k_base_singleton().encode_ordinary(text).len(),
+            Tokenizer::Estimate => text.chars().count().div_ceil(CHARS_PER_TOKEN),
+        }
+    }
+}
+
+impl std::fmt::Display for Tokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Tokenizer::Cl100k => "cl100k",
+            Tokenizer::O200k => "o200k",
+            Tokenizer::Estimate => "estimate",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A writer that passes output through and counts its tokens.
+///
+/// Each write is counted on its own, so the total can be slightly higher
+/// than a count of the whole output at once, but not lower.
+pub(crate) struct TokenCountingWriter<W: Write> {
+    inner: W,
+    tokenizer: Tokenizer,
+    tokens: Rc<Cell<usize>>,
+}
+
+impl<W: Write> TokenCountingWriter<W> {
+    /// Wraps `inner`, returning the writer and the shared running count.
+    pub(crate) fn new(inner: W, tokenizer: Tokenizer) -> (Self, Rc<Cell<usize>>) {
+        let tokens = Rc::new(Cell::new(0));
+        (Self { inner, tokenizer, tokens: Rc::clone(&tokens) }, tokens)
+    }
+}
+
+impl<W: Write> Write for TokenCountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        let count = self.tokenizer.count(&String::from_utf8_lossy(&buf[..written]));
+        self.tokens.set(self.tokens.get() + count);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpe_counts() {
+        assert_eq!(Tokenizer::Cl100k.count(""), 0);
+        assert_eq!(Tokenizer::Cl100k.count("hello world"), 2);
+        assert_eq!(Tokenizer::O200k.count("hello world"), 2);
+        assert_eq!(Tokenizer::Cl100k.count("<|endoftext|>"), 7);
+    }
+
+    #[test]
+    fn test_estimate() {
+        assert_eq!(Tokenizer::Estimate.count(""), 0);
+        assert_eq!(Tokenizer::Estimate.count("abcd"), 1);
+        assert_eq!(Tokenizer::Estimate.count("abcde"), 2);
+        assert_eq!(Tokenizer::Estimate.count("日本語です"), 2);
+    }
+}
@@ -0,0 +1,198 @@
+//! Directory tree diagrams.
+//!
+//! Renders a `tree`-style diagram from an ordered list of relative file
+//! paths. Entries keep the order they are given in, so paths produced by the
+//! directory walker (directories first, then alphabetical) render the same way.
+//! Symlinks are shown with their targets, as in `name -> target`.
+
+use crate::units::format_size;
+
+/// When to show a directory tree diagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeMode {
+    /// No diagram (formats that always include one still do)
+    Off,
+    /// Diagram before the file contents
+    WithContents,
+    /// Diagram with file sizes, without contents
+    Only,
+}
+
+/// A file to show in the tree.
+///
+/// A path ending in `/` names a directory instead; it only annotates a
+/// directory that contains other entries, and adds no line of its own.
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    /// Path relative to the tree root, using `/` as separator
+    pub path: String,
+    /// File size in bytes, if known
+    pub size: Option<u64>,
+    /// Target of the entry if it is a symlink
+    pub symlink: Option<String>,
+}
+
+/// A node in the directory tree.
+#[derive(Debug, Default)]
+struct Node {
+    name: String,
+    size: Option<u64>,
+    symlink: Option<String>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn insert(&mut self, components: &[&str], entry: &TreeEntry) {
+        let Some((first, rest)) = components.split_first() else {
+            return;
+        };
+
+        let index = match self.children.iter().position(|child| child.name == *first) {
+            Some(index) => index,
+            None if entry.path.ends_with('/') => return,
+            None => {
+                self.children.push(Node { name: first.to_string(), ..Node::default() });
+                self.children.len() - 1
+            }
+        };
+
+        let child = &mut self.children[index];
+        if rest.is_empty() {
+            child.size = entry.size;
+            child.symlink = entry.symlink.clone();
+        } else {
+            child.insert(rest, entry);
+        }
+    }
+
+    fn render(&self, prefix: &str, show_sizes: bool, out: &mut String) {
+        for (index, child) in self.children.iter().enumerate() {
+            let last = index + 1 == self.children.len();
+            let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+
+            let target = child.symlink.as_ref().map_or(String::new(), |t| format!(" -> {}", t));
+            if !child.children.is_empty() {
+                out.push_str(&format!("{}{}{}/{}\n", prefix, branch, child.name, target));
+            } else if let (true, Some(size)) = (show_sizes, child.size) {
+                out.push_str(&format!("{}{}{}{} ({})\n", prefix, branch, child.name, target, format_size(size)));
+            } else {
+                out.push_str(&format!("{}{}{}{}\n", prefix, branch, child.name, target));
+            }
+
+            child.render(&format!("{}{}", prefix, indent), show_sizes, out);
+        }
+    }
+
+    /// Counts directories and files below this node.
+    fn count(&self) -> (usize, usize) {
+        self.children.iter().fold((0, 0), |(dirs, files), child| {
+            if child.children.is_empty() {
+                (dirs, files + 1)
+            } else {
+                let (child_dirs, child_files) = child.count();
+                (dirs + 1 + child_dirs, files + child_files)
+            }
+        })
+    }
+}
+
+/// Renders a tree diagram.
+///
+/// # Arguments
+/// * `root` - Label for the root line
+/// * `entries` - Files to show, in display order
+/// * `show_sizes` - Whether to annotate files with their sizes and add a count footer
+pub fn render_tree(root: &str, entries: &[TreeEntry], show_sizes: bool) -> String {
+    let mut tree = Node::default();
+    for entry in entries {
+        let components: Vec<&str> = entry.path
+            .split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .collect();
+        tree.insert(&components, entry);
+    }
+
+    let mut out = format!("{}\n", root);
+    tree.render("", show_sizes, &mut out);
+
+    if show_sizes {
+        let (dirs, files) = tree.count();
+        let total: u64 = entries.iter().filter_map(|e| e.size).sum();
+        out.push_str(&format!(
+            "\n{} {}, {} {} ({})\n",
+            dirs,
+            if dirs == 1 { "directory" } else { "directories" },
+            files,
+            if files == 1 { "file" } else { "files" },
+            format_size(total),
+        ));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64) -> TreeEntry {
+        TreeEntry { path: path.to_string(), size: Some(size), symlink: None }
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let entries = vec![
+            entry("src/io/mod.rs", 10),
+            entry("src/main.rs", 20),
+            entry("Cargo.toml", 30),
+        ];
+
+        let expected = "\
+.
+├── src/
+│   ├── io/
+│   │   └── mod.rs
+│   └── main.rs
+└── Cargo.toml
+";
+        assert_eq!(render_tree(".", &entries, false), expected);
+    }
+
+    #[test]
+    fn test_render_tree_with_sizes() {
+        let entries = vec![entry("src/main.rs", 2048), entry("README.md", 100)];
+
+        let expected = "\
+project/
+├── src/
+│   └── main.rs (2.0 KB)
+└── README.md (100 B)
+
+1 directory, 2 files (2.1 KB)
+";
+        assert_eq!(render_tree("project/", &entries, true), expected);
+    }
+
+    #[test]
+    fn test_render_tree_with_symlinks() {
+        let link = |path: &str, target: &str| TreeEntry {
+            path: path.to_string(),
+            size: None,
+            symlink: Some(target.to_string()),
+        };
+        let entries = vec![
+            entry("vendor/lib.rs", 10),
+            link("latest.rs", "v2.rs"),
+            link("vendor/", "../shared"),
+            link("empty/", "/tmp/empty"),
+        ];
+
+        let expected = "\
+.
+├── vendor/ -> ../shared
+│   └── lib.rs
+└── latest.rs -> v2.rs
+";
+        assert_eq!(render_tree(".", &entries, false), expected);
+    }
+}
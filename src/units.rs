@@ -0,0 +1,92 @@
+//! Human-readable size and count formatting and parsing.
+
+/// Formats a byte count using binary units (`512 B`, `1.5 KB`, `3.0 MB`).
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Parses a size with an optional binary unit suffix (`4096`, `200K`, `1.5M`, `2GB`).
+///
+/// Units are case-insensitive and may be written as `K`, `KB` or `KiB`.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", input))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid size unit in '{}' (use K, M or G)", input)),
+    };
+
+    Ok((value * multiplier as f64).round() as u64)
+}
+
+/// Formats a count with thousands separators (`4,312`).
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(10 * 1024 * 1024), "10.0 MB");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("200K"), Ok(200 * 1024));
+        assert_eq!(parse_size("5m"), Ok(5 * 1024 * 1024));
+        assert_eq!(parse_size("1.5MB"), Ok(1536 * 1024));
+        assert_eq!(parse_size("2 GiB"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(4312), "4,312");
+        assert_eq!(format_count(1234567), "1,234,567");
+    }
+}
@@ -1,14 +1,23 @@
 //! File validation and safety checking utilities.
 
-use std::fs;
 use std::io;
-use std::path::Path;
+
+use crate::detector::FileDetector;
+use crate::probe::FileProbe;
+use crate::units::format_size;
+
+/// Default maximum size of a single file (10 MB).
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Number of leading bytes checked for content unsafe to display.
+const SAFETY_CHECK_BYTES: usize = 1024;
 
 #[derive(Debug)]
 pub enum ValidationError {
     FileNotFound,
     PermissionDenied,
-    FileTooLarge,
+    FileTooLarge { size: u64, limit: u64 },
+    OutputLimitReached { limit: u64 },
     SuspiciousContent,
     IoError(io::Error),
 }
@@ -23,12 +32,34 @@ impl From<io::Error> for ValidationError {
     }
 }
 
+impl ValidationError {
+    /// Returns a short machine-readable name for the kind of error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ValidationError::FileNotFound => "not_found",
+            ValidationError::PermissionDenied => "permission_denied",
+            ValidationError::FileTooLarge { .. } => "too_large",
+            ValidationError::OutputLimitReached { .. } => "output_limit_reached",
+            ValidationError::SuspiciousContent => "suspicious_content",
+            ValidationError::IoError(_) => "io_error",
+        }
+    }
+}
+
 impl std::fmt::Display for ValidationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ValidationError::FileNotFound => write!(f, "File not found or not accessible"),
             ValidationError::PermissionDenied => write!(f, "Permission denied"),
-            ValidationError::FileTooLarge => write!(f, "File is too large"),
+            ValidationError::FileTooLarge { size, limit } => write!(
+                f,
+                "File is too large ({}, limit {})",
+                format_size(*size),
+                format_size(*limit),
+            ),
+            ValidationError::OutputLimitReached { limit } => {
+                write!(f, "Total output size limit reached (limit {})", format_size(*limit))
+            }
             ValidationError::SuspiciousContent => write!(f, "File contains suspicious content"),
             ValidationError::IoError(e) => write!(f, "IO error: {}", e),
         }
@@ -38,32 +69,14 @@ impl std::fmt::Display for ValidationError {
 pub struct FileValidator;
 
 impl FileValidator {
-    /// Validates basic file path requirements.
-    pub fn validate_path(path: &str) -> Result<(), ValidationError> {
-        let path_obj = Path::new(path);
-
-        if !path_obj.exists() {
-            return Err(ValidationError::FileNotFound);
-        }
-
-        if !path_obj.is_file() {
-            return Err(ValidationError::FileNotFound);
-        }
-
-        Ok(())
-    }
-
     /// Checks if file size is within acceptable limits.
     ///
     /// # Arguments
-    /// * `path` - File path to check
-    /// * `max_size_mb` - Maximum size in MB (defaults to 10MB)
-    pub fn validate_file_size(path: &str, max_size_mb: Option<u64>) -> Result<(), ValidationError> {
-        let max_bytes = max_size_mb.unwrap_or(10) * 1024 * 1024;
-
-        let metadata = fs::metadata(path)?;
-        if metadata.len() > max_bytes {
-            return Err(ValidationError::FileTooLarge);
+    /// * `size` - File size in bytes
+    /// * `max_bytes` - Maximum size in bytes
+    pub fn validate_file_size(size: u64, max_bytes: u64) -> Result<(), ValidationError> {
+        if size > max_bytes {
+            return Err(ValidationError::FileTooLarge { size, limit: max_bytes });
         }
 
         Ok(())
@@ -72,17 +85,16 @@ impl FileValidator {
     /// Checks if file content is safe for terminal display.
     ///
     /// Examines the first 1024 bytes for null bytes and excessive control characters.
-    pub fn is_safe_to_display(path: &str) -> Result<bool, ValidationError> {
-        let mut buffer = vec![0u8; 1024];
-        let file = fs::File::open(path)?;
+    /// UTF-16 text is exempt, since its code units naturally contain null bytes.
+    pub fn is_safe_to_display(sample: &[u8]) -> bool {
+        let buffer = &sample[..sample.len().min(SAFETY_CHECK_BYTES)];
 
-        use std::io::Read;
-        let mut handle = file.take(1024);
-        let bytes_read = handle.read(&mut buffer)?;
-        buffer.truncate(bytes_read);
+        if FileDetector::detect_sample_encoding(buffer, true).is_utf16() {
+            return true;
+        }
 
         if buffer.contains(&0) {
-            return Ok(false);
+            return false;
         }
 
         let control_char_count = buffer.iter()
@@ -90,20 +102,24 @@ impl FileValidator {
             .count();
 
         let ratio = control_char_count as f64 / buffer.len() as f64;
-        Ok(ratio < 0.1)
+        ratio < 0.1
     }
 
     /// Performs comprehensive file validation.
     ///
-    /// Combines path validation, size checking, and safety assessment.
-    pub fn quick_validate(path: &str) -> Result<(), ValidationError> {
-        Self::validate_path(path)?;
-        Self::validate_file_size(path, None)?;
+    /// Combines size checking and safety assessment. Only the probe's sample
+    /// is read, and it stays cached for detection.
+    ///
+    /// # Arguments
+    /// * `probe` - The opened file to check
+    /// * `max_file_size` - Maximum size in bytes
+    pub fn quick_validate(probe: &mut FileProbe, max_file_size: u64) -> Result<(), ValidationError> {
+        Self::validate_file_size(probe.size(), max_file_size)?;
 
-        if !Self::is_safe_to_display(path)? {
+        if !Self::is_safe_to_display(probe.sample()?) {
             return Err(ValidationError::SuspiciousContent);
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}